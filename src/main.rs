@@ -15,12 +15,24 @@
 /// - `settings` UNIMPLEMENTED!
 ///     Prints current settings file and defaults to console
 /// 
-/// - `validate` UNIMPLEMENTED!
-///     Validates the syntax of a provided input file 
+/// - `validate`
+///     Lints a provided input file and init file, exiting with a failure status if either has errors 
 /// 
 /// - `simulate`
 ///     Simulates a chemical reaction network 
 /// 
+/// - `ode`
+///     Integrates the deterministic mass action ODEs of a chemical reaction network 
+/// 
+/// - `langevin`
+///     Simulates a chemical reaction network with the chemical Langevin equation 
+/// 
+/// - `hybrid`
+///     Simulates a chemical reaction network integrating fast reactions deterministically and firing slow reactions exactly 
+/// 
+/// - `fsp`
+///     Computes the exact probability distribution of a chemical reaction network's outcomes by finite state projection 
+/// 
 /// - `help`
 ///     Prints Usage to command line
 /// 
@@ -42,12 +54,51 @@
 /// - `-Time <Maximum Runtime>`
 ///     Specifies the maximum time the simulation is allowed to run for. 
 /// 
+/// - `-EndTime <Simulated Time>`
+///     Specifies the simulated time to integrate the ODEs until. If omitted integration runs until a steady state is reached.
+/// 
+/// - `-TimeStep <Step Size>`
+///     Specifies the fixed step size used by the chemical Langevin equation and the fast reactions of the hybrid simulator. 
+/// 
+/// - `-Histogram <Histogram File Path>`
+///     Specifies a file to write the histogram and quantiles of every species' final counts to, as CSV or JSON. 
+/// 
+/// - `-Bins <Number Of Bins>`
+///     Specifies the maximum number of bins in each species' histogram. 
+/// 
+/// - `-Quantiles <Quantile Levels>`
+///     Specifies a comma separated list of the quantiles reported for every species. 
+/// 
+/// - `-MaxStates <Number Of States>`
+///     Specifies the bound on the number of states the finite state projection enumerates. 
+/// 
+/// - `-OdeSolver <Solver>`
+///     Specifies the ODE solver to use: `auto`, `rk45`, or `rosenbrock`. By default stiffness is detected automatically.
+/// 
+/// - `-Algorithm <Simulation Algorithm>`
+///     Specifies the stochastic simulation algorithm to use: `direct`, `next-reaction`, or `tau-leap`.
+/// 
+/// - `-Seed <Seed>`
+///     Specifies the master seed of the random number generators, making stochastic results reproducible. 
+/// 
+/// - `-Precision <Species=HalfWidth>`
+///     Specifies confidence interval half-widths to run trials until, instead of a fixed number of trials. 
+/// 
+/// - `-Confidence <Level>`
+///     Specifies the confidence level of precision targets. 
+/// 
+/// - `-Duplicates <Duplicate Policy>`
+///     Specifies how reactions written more than once are handled: `keep`, `merge`, or `reject`. By default they are kept as separate reactions.
+/// 
+/// - `-BigCounts`
+///     Counts species with arbitrary precision so that networks whose counts grow without bound never overflow. 
+/// 
 
 
 // Import necessary modules
 use std::path::PathBuf;
 use structopt::StructOpt;
-use marlea_engine;
+use marlea_engine::{MarleaEngine, trial::reaction_network::{SimulationAlgorithm, duplicates::DuplicatePolicy}, parser::diagnostic::{Diagnostic, Severity}, ode::{OdeSettings, OdeSolver}, langevin::LangevinSettings, hybrid::HybridSettings, fsp::FspSettings, statistics::{ReportSettings, precision::{PrecisionSettings, PrecisionTarget}}};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Marlea", about = "A command line program for simulating DNA based chemical reaction networks")]
@@ -61,6 +112,14 @@ enum Query {
     Validate,
     #[structopt(name = "simulate")]
     Simulate,
+    #[structopt(name = "ode")]
+    Ode,
+    #[structopt(name = "langevin")]
+    Langevin,
+    #[structopt(name = "hybrid")]
+    Hybrid,
+    #[structopt(name = "fsp")]
+    Fsp,
     #[structopt(name = "help")]
     Help,
 }
@@ -87,6 +146,32 @@ struct MarleaOpts {
     max_runtime: Option<u64>,
     #[structopt(short="-s", long="--stability_tolerance")]
     max_semi_stable_steps: Option<i32>,
+    #[structopt(short="-a", long="--algorithm")]
+    algorithm: Option<SimulationAlgorithm>,
+    #[structopt(long="--duplicates")]
+    duplicates: Option<DuplicatePolicy>,
+    #[structopt(long="--end-time")]
+    end_time: Option<f64>,
+    #[structopt(long="--ode-solver")]
+    ode_solver: Option<OdeSolver>,
+    #[structopt(long="--time-step", parse(try_from_str = parse_time_step))]
+    time_step: Option<f64>,
+    #[structopt(long="--max-states")]
+    max_states: Option<usize>,
+    #[structopt(long="--histogram")]
+    histogram_file: Option<String>,
+    #[structopt(long="--bins", parse(try_from_str = parse_bins))]
+    bins: Option<usize>,
+    #[structopt(long="--quantiles", use_delimiter = true, parse(try_from_str = parse_quantile))]
+    quantiles: Vec<f64>,
+    #[structopt(long="--seed")]
+    seed: Option<u64>,
+    #[structopt(long="--precision", use_delimiter = true)]
+    precision: Vec<PrecisionTarget>,
+    #[structopt(long="--confidence", parse(try_from_str = parse_confidence))]
+    confidence: Option<f64>,
+    #[structopt(long="--big-counts")]
+    big_counts: bool,
 }
 
 
@@ -99,12 +184,80 @@ impl std::str::FromStr for Query {
             //"settings" => Ok(Query::Settings),
             "validate" => Ok(Query::Validate),
             "simulate" => Ok(Query::Simulate),
+            "ode" => Ok(Query::Ode),
+            "langevin" => Ok(Query::Langevin),
+            "hybrid" => Ok(Query::Hybrid),
+            "fsp" => Ok(Query::Fsp),
             "help" => Ok(Query::Help),
             _ => Err(format!("Invalid query '{}'", s)),
         }
     }
 }
 
+// parses the fixed step size of the langevin and hybrid queries, which must be finite and positive
+fn parse_time_step(s: &str) -> Result<f64, String> {
+    let invalid = || format!("Invalid time step '{}', expected a positive number such as '0.001'", s);
+    let time_step = s.trim().parse::<f64>().map_err(|_| invalid())?;
+    if !time_step.is_finite() || time_step <= 0.0 {
+        return Err(invalid());
+    }
+    return Ok(time_step);
+}
+
+// parses the maximum number of bins in each species' histogram, which must be at least one
+fn parse_bins(s: &str) -> Result<usize, String> {
+    let invalid = || format!("Invalid number of bins '{}', expected a positive whole number such as '10'", s);
+    let bins = s.trim().parse::<usize>().map_err(|_| invalid())?;
+    if bins == 0 {
+        return Err(invalid());
+    }
+    return Ok(bins);
+}
+
+// parses a single quantile level, which must lie between 0 and 1
+fn parse_quantile(s: &str) -> Result<f64, String> {
+    let invalid = || format!("Invalid quantile '{}', expected a value between 0 and 1 such as '0.5'", s);
+    let quantile = s.trim().parse::<f64>().map_err(|_| invalid())?;
+    if !(0.0..=1.0).contains(&quantile) {
+        return Err(invalid());
+    }
+    return Ok(quantile);
+}
+
+// parses the confidence level of precision targets, which must lie strictly between 0 and 1
+fn parse_confidence(s: &str) -> Result<f64, String> {
+    let invalid = || format!("Invalid confidence level '{}', expected a value between 0 and 1 such as '0.95'", s);
+    let confidence = s.trim().parse::<f64>().map_err(|_| invalid())?;
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return Err(invalid());
+    }
+    return Ok(confidence);
+}
+
+// prints every problem found in the input file and exits with a failure status
+fn exit_with_diagnostics(diagnostics: Vec<Diagnostic>) -> ! {
+    for diagnostic in &diagnostics {
+        println!("{}\n", diagnostic);
+    }
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+    println!("could not parse the input files, found {} errors", errors);
+    std::process::exit(1);
+}
+
+// prints the warnings found in the input files once they parse, or every problem found in them if they do not
+fn engine_or_exit(engine: Result<MarleaEngine, Vec<Diagnostic>>) -> MarleaEngine {
+    let engine = engine.unwrap_or_else(|diagnostics| exit_with_diagnostics(diagnostics));
+    for warning in engine.get_warnings() {
+        println!("{}\n", warning);
+    }
+    return engine;
+}
+
+// prints an error which stopped a query and exits with a failure status
+fn exit_with_error(error: String) -> ! {
+    println!("Error: {}", error);
+    std::process::exit(1);
+}
 
 fn main () {
     let opts = MarleaOpts::from_args();
@@ -112,7 +265,7 @@ fn main () {
     // Match query and perform appropriate action
     match opts.query {
         // Just print Usage information if `help` query is provided
-        Query::Help => println!("{}", MarleaOpts::clap().about(&*format!(
+        Query::Help => println!("{}", MarleaOpts::clap().about(
             "A command line program for simulating DNA based chemical reaction networks.
             
             This program takes some CSV (other formats remain unimplemented) as input and performs stochastic simulation on the provided system,
@@ -120,7 +273,7 @@ fn main () {
             
             Usage: marlea <QUERY> <INPUT_FILE> [Options]
             Arguments:
-                <QUERY>                          Specify the operation/query to perform. Possible values: \"settings\", \"validate\", \"simulate\", \"help\".
+                <QUERY>                          Specify the operation/query to perform. Possible values: \"settings\", \"validate\", \"simulate\", \"ode\", \"langevin\", \"hybrid\", \"fsp\", \"help\".
                 <INPUT_FILE>                     Input file path to use.
             
             Options:
@@ -128,12 +281,25 @@ fn main () {
                 -o, --out <FILE_NAME>    Specifies a file where the program should write its results. If omitted program will only print to the command line.
                 -t, --trials <NUM_TRIALS>    Specifies the number of times the simulation should simulate the chemical reaction network.
                 -r, --runtime <MAX_RUNTIME> Specifies the maximum time the simulation is allowed to run for in seconds.
-                -s, --stability_tolerance <SEMI_STABLE_TRIALS> **Advanced** Specifies the number of successive semi stable trials to run before terminated"
-        ))),
+                -s, --stability_tolerance <SEMI_STABLE_TRIALS> **Advanced** Specifies the number of successive semi stable trials to run before terminated
+                -a, --algorithm <ALGORITHM>  Specifies the stochastic simulation algorithm. Possible values: \"direct\" (default), \"next-reaction\", \"tau-leap\".
+                --duplicates <POLICY>  Specifies how reactions written more than once, with the same or different rates, are handled. Possible values: \"keep\" (default) keeps them as separate reactions, \"merge\" merges them into one reaction with the sum of their rates, \"reject\" rejects the input file. Every repeated reaction is reported with its line numbers.
+                --end-time <TIME>  Specifies the simulated time the \"ode\", \"langevin\", and \"hybrid\" queries integrate until, and the time the \"fsp\" query computes probabilities at. If omitted the \"ode\" query runs until a steady state is reached and the \"fsp\" query computes the probability of each final outcome.
+                --time-step <STEP>  Specifies the fixed step size used by the \"langevin\" and \"hybrid\" queries. By default this is 0.001
+                --histogram <FILE_NAME>  Specifies a file to write the histogram and quantiles of every species' final counts to, as CSV or JSON.
+                --bins <NUM_BINS>  Specifies the maximum number of bins in each species' histogram. By default this is 10
+                --quantiles <LEVELS>  Specifies a comma separated list of the quantiles reported for every species. By default this is 0.05,0.25,0.5,0.75,0.95
+                --max-states <NUM_STATES>  Specifies the bound on the number of states enumerated by the \"fsp\" query. By default this is 100000
+                --ode-solver <SOLVER>  Specifies the solver used by the \"ode\" query. Possible values: \"auto\" (default), \"rk45\", \"rosenbrock\".
+                --seed <SEED>  Specifies the master seed every trial derives its random numbers from, so that the \"simulate\", \"langevin\", and \"hybrid\" queries are reproducible. If omitted a random seed is drawn and printed.
+                --precision <TARGETS>  Specifies a comma separated list of confidence interval half-widths such as Y=0.5; trials are run until the mean of every listed species is known to within its half-width, with --trials as the maximum number of trials (10000 by default).
+                --confidence <LEVEL>  Specifies the confidence level of precision targets. By default this is 0.95
+                --big-counts  Makes the \"simulate\" query count species with arbitrary precision, for networks whose counts grow past 18446744073709551615. Without it a trial which would overflow a count stops with an error naming the reaction."
+        )),
 
         // If `simulate` query is provided, create new instance of MarleaEngine with parsed options, then run it
         Query::Simulate => {
-            let engine = marlea_engine::MarleaEngine::new(
+            let mut engine = engine_or_exit(marlea_engine::MarleaEngine::try_new(
                 opts.input_file.to_string_lossy().into_owned(), 
                 opts.init_file, 
                 opts.output_file, 
@@ -141,14 +307,186 @@ fn main () {
                 opts.num_trials, 
                 opts.max_runtime,
                 opts.max_semi_stable_steps,
-            );
+                opts.algorithm,
+                opts.duplicates,
+            ));
+            if let Some(seed) = opts.seed {
+                engine.set_seed(seed);
+            }
+            let defaults = ReportSettings::default();
+            engine.set_report_settings(ReportSettings{
+                quantiles: if opts.quantiles.is_empty() { defaults.quantiles } else { opts.quantiles },
+                bins: opts.bins.unwrap_or(defaults.bins),
+                histogram_path: opts.histogram_file,
+            });
+            if !opts.precision.is_empty() {
+                let defaults = PrecisionSettings::default();
+                if let Err(error) = engine.set_precision_settings(PrecisionSettings{
+                    targets: opts.precision,
+                    confidence: opts.confidence.unwrap_or(defaults.confidence),
+                    max_trials: opts.num_trials.unwrap_or(defaults.max_trials),
+                    ..defaults
+                }) {
+                    exit_with_error(error);
+                }
+            } else if opts.confidence.is_some() {
+                println!("WARNING: --confidence has no effect without --precision");
+            }
             // Run MarleaEngine
-            engine.run();
+            let result = if opts.big_counts {
+                engine.run_big_counts().map(|_| ())
+            } else {
+                engine.run().map(|_| ())
+            };
+            if let Err(error) = result {
+                exit_with_error(error);
+            }
+        },
+
+        // If `ode` query is provided, integrate the deterministic mass action ODEs of the network instead
+        Query::Ode => {
+            let engine = engine_or_exit(marlea_engine::MarleaEngine::try_new(
+                opts.input_file.to_string_lossy().into_owned(), 
+                opts.init_file, 
+                opts.output_file, 
+                opts.output_timeline,
+                opts.num_trials, 
+                opts.max_runtime,
+                opts.max_semi_stable_steps,
+                opts.algorithm,
+                opts.duplicates,
+            ));
+            if let Err(error) = engine.run_ode(OdeSettings{
+                end_time: opts.end_time, 
+                solver: opts.ode_solver.unwrap_or_default(), 
+                ..OdeSettings::default()
+            }) {
+                exit_with_error(error);
+            }
+        },
+
+        // If `langevin` query is provided, simulate trials of the chemical Langevin equation instead
+        Query::Langevin => {
+            let end_time = opts.end_time.unwrap_or_else(|| exit_with_error(String::from("the langevin query requires an end time, provide one with --end-time")));
+            let mut engine = engine_or_exit(marlea_engine::MarleaEngine::try_new(
+                opts.input_file.to_string_lossy().into_owned(), 
+                opts.init_file, 
+                opts.output_file, 
+                opts.output_timeline,
+                opts.num_trials, 
+                opts.max_runtime,
+                opts.max_semi_stable_steps,
+                opts.algorithm,
+                opts.duplicates,
+            ));
+            if let Some(seed) = opts.seed {
+                engine.set_seed(seed);
+            }
+            let defaults = LangevinSettings::default();
+            if let Err(error) = engine.run_langevin(LangevinSettings{
+                end_time, 
+                time_step: opts.time_step.unwrap_or(defaults.time_step),
+                ..defaults
+            }) {
+                exit_with_error(error);
+            }
+        },
+
+        // If `hybrid` query is provided, simulate trials partitioned between deterministic fast and exact slow reactions instead
+        Query::Hybrid => {
+            let end_time = opts.end_time.unwrap_or_else(|| exit_with_error(String::from("the hybrid query requires an end time, provide one with --end-time")));
+            let mut engine = engine_or_exit(marlea_engine::MarleaEngine::try_new(
+                opts.input_file.to_string_lossy().into_owned(), 
+                opts.init_file, 
+                opts.output_file, 
+                opts.output_timeline,
+                opts.num_trials, 
+                opts.max_runtime,
+                opts.max_semi_stable_steps,
+                opts.algorithm,
+                opts.duplicates,
+            ));
+            if let Some(seed) = opts.seed {
+                engine.set_seed(seed);
+            }
+            let defaults = HybridSettings::default();
+            if let Err(error) = engine.run_hybrid(HybridSettings{
+                end_time, 
+                time_step: opts.time_step.unwrap_or(defaults.time_step),
+                ..defaults
+            }) {
+                exit_with_error(error);
+            }
         },
 
-        // Print error message if unsupported query is requested
-        _ => println!("Error: Unsupported query requested.")
+        // If `fsp` query is provided, compute the probability distribution of the network by finite state projection instead
+        Query::Fsp => {
+            let engine = engine_or_exit(marlea_engine::MarleaEngine::try_new(
+                opts.input_file.to_string_lossy().into_owned(), 
+                opts.init_file, 
+                opts.output_file, 
+                opts.output_timeline,
+                opts.num_trials, 
+                opts.max_runtime,
+                opts.max_semi_stable_steps,
+                opts.algorithm,
+                opts.duplicates,
+            ));
+            let defaults = FspSettings::default();
+            if let Err(error) = engine.run_fsp(FspSettings{
+                end_time: opts.end_time, 
+                max_states: opts.max_states.unwrap_or(defaults.max_states),
+                ..defaults
+            }) {
+                exit_with_error(error);
+            }
+        },
+
+        // If `validate` query is provided, report every problem in the input and init files without simulating them
+        Query::Validate => {
+            let input_path = opts.input_file.to_string_lossy().into_owned();
+            let diagnostics = marlea_engine::validate::validate(input_path.clone(), opts.init_file, opts.duplicates.unwrap_or_default());
+            for diagnostic in &diagnostics {
+                println!("{}\n", diagnostic);
+            }
+
+            let count = |severity: Severity| diagnostics.iter().filter(|diagnostic| diagnostic.get_severity() == severity).count();
+            let (errors, warnings, notes) = (count(Severity::Error), count(Severity::Warning), count(Severity::Note));
+            if errors > 0 {
+                println!("{} is invalid, found {} errors, {} warnings, and {} notes", input_path, errors, warnings, notes);
+                std::process::exit(1);
+            }
+            println!("{} is valid, found {} warnings and {} notes", input_path, warnings, notes);
+        },
     }
 
     return; 
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_range_quantiles_are_rejected() {
+        assert_eq!(parse_quantile("0.5"), Ok(0.5));
+        assert_eq!(parse_quantile("1"), Ok(1.0));
+        assert_eq!(parse_quantile("1.5"), Err(String::from("Invalid quantile '1.5', expected a value between 0 and 1 such as '0.5'")));
+        assert!(parse_quantile("-0.1").is_err());
+        assert!(parse_quantile("NaN").is_err());
+
+        let result = MarleaOpts::from_iter_safe(["marlea", "simulate", "network.csv", "--quantiles", "0.5,95"]);
+        assert!(result.err().unwrap().message.contains("Invalid quantile '95'"));
+    }
+
+    #[test]
+    fn test_zero_bins_are_rejected() {
+        assert_eq!(parse_bins("10"), Ok(10));
+        assert_eq!(parse_bins("0"), Err(String::from("Invalid number of bins '0', expected a positive whole number such as '10'")));
+        assert!(parse_bins("-1").is_err());
+
+        let result = MarleaOpts::from_iter_safe(["marlea", "simulate", "network.csv", "--bins", "0"]);
+        assert!(result.err().unwrap().message.contains("Invalid number of bins '0'"));
+    }
+}
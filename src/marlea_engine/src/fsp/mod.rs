@@ -0,0 +1,347 @@
+/// # Description
+/// Finite state projection (FSP) solution of the chemical master equation of a chemical reaction network.
+/// Rather than estimating the distribution of outcomes from a number of stochastic trials,
+/// every state reachable from the initial solution is enumerated and the probability of each is computed directly.
+///
+/// The reachable states are enumerated breadth first up to a bound on the number of states.
+/// Together they form a continuous time Markov chain whose generator is stored sparsely as the outgoing transitions of every state.
+/// Transitions which would leave the enumerated states are sent to a single absorbing sink instead,
+/// so the probability which ends up in the sink bounds the error introduced by truncating the state space (Munsky and Khammash 2006).
+///
+/// - Transient probabilities at some time are computed by uniformization, `p(t) = sum_k Poisson(k; qt) p(0) P^k` where `P = I + Q/q`
+/// - Absorbing probabilities, the distribution of final outcomes, are computed by propagating probability through the embedded
+///   jump chain until all of it has been absorbed by a state where no reaction is possible
+
+use std::collections::HashMap;
+use crate::ode::MassActionSystem;
+
+/// Settings for a finite state projection
+/// - `end_time`: the time transient probabilities are computed at, if None the probability of each final outcome is computed instead
+/// - `max_states`: the bound on the number of states enumerated
+/// - `tolerance`: the probability which may be left unaccounted for by the iterative solvers
+/// - `max_iterations`: the number of iterations after which the solvers give up
+#[derive(Debug, Clone, Copy)]
+pub struct FspSettings {
+    pub end_time: Option<f64>,
+    pub max_states: usize,
+    pub tolerance: f64,
+    pub max_iterations: usize,
+}
+
+impl Default for FspSettings {
+    fn default() -> Self {
+        Self {
+            end_time: None,
+            max_states: 100_000,
+            tolerance: 1e-9,
+            max_iterations: 1_000_000,
+        }
+    }
+}
+
+/// The reachable states of a network projected onto a finite set, and the sparse generator of the Markov chain between them
+/// - `states`: the species counts of every enumerated state, the initial state is always at index 0
+/// - `transitions`: the target state and rate of every transition leaving each state
+/// - `exit_rates`: the total rate of leaving each state, including into the sink
+/// - `sink_rates`: the rate at which each state leaves the projection
+pub struct StateSpace {
+    species: Vec<String>,
+    states: Vec<Vec<u64>>,
+    transitions: Vec<Vec<(usize, f64)>>,
+    exit_rates: Vec<f64>,
+    sink_rates: Vec<f64>,
+}
+
+impl StateSpace {
+
+    /// enumerates every state reachable from `initial_state` breadth first until `max_states` states have been found
+    pub fn from(system: &MassActionSystem, initial_state: Vec<u64>, max_states: usize) -> Self {
+        let mut indices: HashMap<Vec<u64>, usize> = HashMap::from([(initial_state.clone(), 0)]);
+        let mut states = vec![initial_state];
+        let mut transitions = Vec::new();
+        let mut exit_rates = Vec::new();
+        let mut sink_rates = Vec::new();
+
+        // states are appended in the order they are discovered so walking the list is a breadth first search
+        let mut next = 0;
+        while next < states.len() {
+            let state = states[next].clone();
+            let float_state: Vec<f64> = state.iter().map(|count| *count as f64).collect();
+            let mut state_transitions: Vec<(usize, f64)> = Vec::new();
+            let mut exit_rate = 0.0;
+            let mut sink_rate = 0.0;
+
+            for reaction in 0..system.num_reactions() {
+                let propensity = system.get_propensity(reaction, &float_state);
+                let net_changes = system.get_net_changes(reaction);
+                // reactions which change nothing never leave the state
+                if propensity <= 0.0 || net_changes.is_empty() {
+                    continue;
+                }
+
+                let mut target = state.clone();
+                for (index, net_change) in net_changes {
+                    target[*index] = u64::try_from(target[*index] as i128 + *net_change as i128)
+                        .unwrap_or_else(|_| panic!("the count of {} overflowed when enumerating states", system.get_species()[*index]));
+                }
+                exit_rate += propensity;
+
+                let target_index = match indices.get(&target) {
+                    Some(target_index) => Some(*target_index),
+                    None if states.len() < max_states => {
+                        indices.insert(target.clone(), states.len());
+                        states.push(target);
+                        Some(states.len() - 1)
+                    }
+                    None => None,
+                };
+                match target_index {
+                    Some(target_index) => match state_transitions.iter_mut().find(|(existing, _)| *existing == target_index) {
+                        // parallel reactions between the same states are a single transition
+                        Some((_, rate)) => *rate += propensity,
+                        None => state_transitions.push((target_index, propensity)),
+                    },
+                    None => sink_rate += propensity,
+                }
+            }
+
+            transitions.push(state_transitions);
+            exit_rates.push(exit_rate);
+            sink_rates.push(sink_rate);
+            next += 1;
+        }
+
+        return Self{species: system.get_species().to_vec(), states, transitions, exit_rates, sink_rates};
+    }
+
+    /// returns the names of all species in the order used by states
+    pub fn get_species(&self) -> &[String] {
+        return &self.species;
+    }
+
+    /// returns the species counts of every enumerated state
+    pub fn get_states(&self) -> &[Vec<u64>] {
+        return &self.states;
+    }
+
+    pub fn num_states(&self) -> usize {
+        return self.states.len();
+    }
+
+    /// returns true if any transition leaves the projection, in which case probabilities may be underestimated
+    pub fn is_truncated(&self) -> bool {
+        return self.sink_rates.iter().any(|rate| *rate > 0.0);
+    }
+
+    /// returns the probability of every state at `time` by uniformization, starting with certainty in the initial state
+    pub fn transient(&self, time: f64, tolerance: f64, max_iterations: usize) -> Vec<f64> {
+        let mut probabilities = vec![0.0; self.num_states()];
+        probabilities[0] = 1.0;
+
+        let uniform_rate = self.exit_rates.iter().cloned().fold(0.0, f64::max);
+        if uniform_rate <= 0.0 || time <= 0.0 {
+            return probabilities;
+        }
+
+        // sum Poisson(k; qt) p(0) P^k with the Poisson weights tracked in log space so early terms of a large qt underflow cleanly
+        let mean_jumps = uniform_rate * time;
+        let mut log_weight = -mean_jumps;
+        let mut total_weight = 0.0;
+        let mut transient = vec![0.0; self.num_states()];
+        let mut next_probabilities = vec![0.0; self.num_states()];
+
+        for jumps in 0..max_iterations {
+            let weight = log_weight.exp();
+            total_weight += weight;
+            for (sum, probability) in transient.iter_mut().zip(&probabilities) {
+                *sum += weight * probability;
+            }
+            if jumps as f64 > mean_jumps && 1.0 - total_weight <= tolerance {
+                return transient;
+            }
+
+            // p P, probability sent to the sink is dropped
+            for (state, probability) in probabilities.iter().enumerate() {
+                next_probabilities[state] = probability * (1.0 - self.exit_rates[state] / uniform_rate);
+            }
+            for (state, probability) in probabilities.iter().enumerate() {
+                if *probability > 0.0 {
+                    for (target, rate) in &self.transitions[state] {
+                        next_probabilities[*target] += probability * rate / uniform_rate;
+                    }
+                }
+            }
+            std::mem::swap(&mut probabilities, &mut next_probabilities);
+            log_weight += mean_jumps.ln() - ((jumps + 1) as f64).ln();
+        }
+
+        println!("WARNING: FSP uniformization stopped after {} iterations with {} of the Poisson weight unaccounted for", max_iterations, 1.0 - total_weight);
+        return transient;
+    }
+
+    /// returns the probability of ending in every state, which is only non zero for states where no reaction is possible,
+    /// along with the probability which was not absorbed within `max_iterations` jumps
+    pub fn absorption(&self, tolerance: f64, max_iterations: usize) -> (Vec<f64>, f64) {
+        let mut absorbed = vec![0.0; self.num_states()];
+        let mut probabilities = vec![0.0; self.num_states()];
+        probabilities[0] = 1.0;
+        let mut next_probabilities = vec![0.0; self.num_states()];
+
+        for _ in 0..max_iterations {
+            // collect the probability which has reached absorbing states
+            for (state, probability) in probabilities.iter_mut().enumerate() {
+                if self.exit_rates[state] <= 0.0 {
+                    absorbed[state] += *probability;
+                    *probability = 0.0;
+                }
+            }
+
+            let remaining: f64 = probabilities.iter().sum();
+            if remaining <= tolerance {
+                return (absorbed, remaining);
+            }
+
+            // take one jump of the embedded chain, probability sent to the sink is dropped
+            next_probabilities.iter_mut().for_each(|probability| *probability = 0.0);
+            for (state, probability) in probabilities.iter().enumerate() {
+                if *probability > 0.0 {
+                    for (target, rate) in &self.transitions[state] {
+                        next_probabilities[*target] += probability * rate / self.exit_rates[state];
+                    }
+                }
+            }
+            std::mem::swap(&mut probabilities, &mut next_probabilities);
+        }
+
+        let remaining: f64 = probabilities.iter().sum();
+        println!("WARNING: FSP stopped after {} jumps with {} probability not yet absorbed, the network may never reach a final state", max_iterations, remaining);
+        return (absorbed, remaining);
+    }
+}
+
+/// A probability distribution over the states of a network
+/// - `outcomes`: every state with a non zero probability, most likely first
+/// - `truncation_error`: the probability lost by truncating the state space, every reported probability is exact to within this bound
+/// - `unresolved_probability`: the probability which the solver could not assign to an outcome within its iteration limit
+pub struct ProbabilityDistribution {
+    species: Vec<String>,
+    outcomes: Vec<(Vec<u64>, f64)>,
+    truncation_error: f64,
+    unresolved_probability: f64,
+    num_states: usize,
+}
+
+impl ProbabilityDistribution {
+
+    /// returns the names of all species in the order used by outcomes
+    pub fn get_species(&self) -> &[String] {
+        return &self.species;
+    }
+
+    /// returns every outcome with a non zero probability, most likely first
+    pub fn get_outcomes(&self) -> &[(Vec<u64>, f64)] {
+        return &self.outcomes;
+    }
+
+    pub fn get_truncation_error(&self) -> f64 {
+        return self.truncation_error;
+    }
+
+    pub fn get_unresolved_probability(&self) -> f64 {
+        return self.unresolved_probability;
+    }
+
+    /// returns the number of states the projection enumerated
+    pub fn num_states(&self) -> usize {
+        return self.num_states;
+    }
+
+    /// returns the probability of a single state, 0 if it is not an outcome
+    pub fn get_probability(&self, state: &[u64]) -> f64 {
+        return self.outcomes.iter()
+            .find(|(outcome, _)| outcome.as_slice() == state)
+            .map_or(0.0, |(_, probability)| *probability);
+    }
+}
+
+/// Solves the chemical master equation of a mass action system by finite state projection from the given initial counts
+pub fn solve(system: &MassActionSystem, initial_state: Vec<u64>, settings: &FspSettings) -> ProbabilityDistribution {
+    let state_space = StateSpace::from(system, initial_state, settings.max_states);
+    if state_space.is_truncated() {
+        println!("WARNING: state space truncated at {} states, some probability will be lost to the sink", settings.max_states);
+    }
+
+    let (probabilities, unresolved_probability) = match settings.end_time {
+        Some(end_time) => (state_space.transient(end_time, settings.tolerance, settings.max_iterations), 0.0),
+        None => state_space.absorption(settings.tolerance, settings.max_iterations),
+    };
+
+    let total: f64 = probabilities.iter().sum();
+    let truncation_error = (1.0 - total - unresolved_probability).max(0.0);
+
+    let mut outcomes: Vec<(Vec<u64>, f64)> = state_space.get_states().iter().cloned()
+        .zip(probabilities)
+        .filter(|(_, probability)| *probability > 0.0)
+        .collect();
+    outcomes.sort_by(|first, second| second.1.total_cmp(&first.1).then_with(|| first.0.cmp(&second.0)));
+
+    return ProbabilityDistribution{
+        species: state_space.get_species().to_vec(),
+        outcomes,
+        truncation_error,
+        unresolved_probability,
+        num_states: state_space.num_states(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::trial::reaction_network::reaction::{Reaction, term::Term};
+
+    fn reaction(reactants: &[(&str, u32)], products: &[(&str, u32)], rate: f64) -> Reaction {
+        let terms = |terms: &[(&str, u32)]| terms.iter().map(|(name, coefficient)| Term::new(name.to_string(), *coefficient)).collect::<HashSet<Term>>();
+        return Reaction::new(terms(reactants), terms(products), rate);
+    }
+
+    #[test]
+    fn test_absorption_probabilities() {
+        // A => B, 1 competes with A => C, 3
+        let system = MassActionSystem::from(&[reaction(&[("A", 1)], &[("B", 1)], 1.0), reaction(&[("A", 1)], &[("C", 1)], 3.0)]);
+        let distribution = solve(&system, vec![1, 0, 0], &FspSettings::default());
+
+        assert_eq!(distribution.num_states(), 3);
+        assert!((distribution.get_probability(&[0, 1, 0]) - 0.25).abs() < 1e-12);
+        assert!((distribution.get_probability(&[0, 0, 1]) - 0.75).abs() < 1e-12);
+        assert_eq!(distribution.get_truncation_error(), 0.0);
+    }
+
+    #[test]
+    fn test_transient_probabilities() {
+        // A => B, 1 from a single A leaves A unreacted with probability e^-t
+        let system = MassActionSystem::from(&[reaction(&[("A", 1)], &[("B", 1)], 1.0)]);
+        let settings = FspSettings{end_time: Some(1.0), ..FspSettings::default()};
+        let distribution = solve(&system, vec![1, 0], &settings);
+
+        assert!((distribution.get_probability(&[1, 0]) - (-1.0_f64).exp()).abs() < 1e-9);
+        assert!((distribution.get_probability(&[0, 1]) - (1.0 - (-1.0_f64).exp())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_truncation_error() {
+        // => A, 1 is unbounded so the projection must be truncated
+        let system = MassActionSystem::from(&[reaction(&[], &[("A", 1)], 1.0)]);
+        let settings = FspSettings{end_time: Some(10.0), max_states: 5, ..FspSettings::default()};
+        let distribution = solve(&system, vec![0], &settings);
+
+        // the probability of more than 4 arrivals of a Poisson process with mean 10 is lost
+        let mut within_projection = 0.0;
+        let mut term = (-10.0_f64).exp();
+        for arrivals in 0..5 {
+            within_projection += term;
+            term *= 10.0 / (arrivals + 1) as f64;
+        }
+        assert!((distribution.get_truncation_error() - (1.0 - within_projection)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,222 @@
+/// # Description
+/// Hybrid stochastic/deterministic simulation of a chemical reaction network.
+/// Networks with a few low count "signal" species and many high count "fuel" species are expensive to simulate exactly
+/// because almost every step fires a fuel reaction. The hybrid simulator partitions reactions from the current counts:
+///
+/// - fast reactions fire many times per time step and only involve species with large counts,
+///   these are integrated deterministically with a fourth order Runge-Kutta step
+/// - slow reactions are every other reaction, these are fired exactly one at a time as in the SSA
+///
+/// Slow reactions fire when the integral of their total propensity reaches an exponentially distributed threshold,
+/// which accounts for their propensities changing as the fast reactions evolve (Haseltine and Rawlings 2002, Salis and Kaznessis 2005).
+/// The partition is re-evaluated after every step so that reactions move between sets as counts change.
+
+use rand::Rng;
+use crate::ode::{MassActionSystem, OdeTrajectory};
+
+/// Settings for a hybrid trial
+/// - `end_time`: the simulated time every trial is run until
+/// - `time_step`: the step used to integrate fast reactions
+/// - `fast_firings`: a reaction is only fast if it is expected to fire at least this many times per time step
+/// - `fast_population`: a reaction is only fast if every species it involves has at least this count
+/// - `record_trajectory`: whether every step is recorded, otherwise only the initial and final states are kept
+#[derive(Debug, Clone, Copy)]
+pub struct HybridSettings {
+    pub end_time: f64,
+    pub time_step: f64,
+    pub fast_firings: f64,
+    pub fast_population: f64,
+    pub record_trajectory: bool,
+}
+
+impl Default for HybridSettings {
+    fn default() -> Self {
+        Self {
+            end_time: 1.0,
+            time_step: 1e-3,
+            fast_firings: 10.0,
+            fast_population: 100.0,
+            record_trajectory: false,
+        }
+    }
+}
+
+/// Simulates a single hybrid trial from the given initial state
+pub fn simulate<R: Rng>(system: &MassActionSystem, initial_state: Vec<f64>, settings: &HybridSettings, rng: &mut R) -> OdeTrajectory {
+    let mut trajectory = OdeTrajectory::new(system.get_species().to_vec());
+    let mut state = initial_state;
+    let mut time = 0.0;
+    trajectory.push(time, state.clone());
+
+    // integral of the slow propensity since the last slow firing, and the threshold at which the next one fires
+    let mut slow_integral = 0.0;
+    let mut slow_threshold = sample_threshold(rng);
+
+    while time < settings.end_time {
+        let (fast, slow) = partition(system, &state, settings);
+        let slow_propensity: f64 = slow.iter().map(|reaction| system.get_propensity(*reaction, &state)).sum();
+
+        if fast.is_empty() {
+            // nothing changes between slow firings so they can be fired exactly without integrating
+            if slow_propensity <= 0.0 {
+                break;
+            }
+            let waiting_time = (slow_threshold - slow_integral) / slow_propensity;
+            if time + waiting_time > settings.end_time {
+                time = settings.end_time;
+                break;
+            }
+
+            time += waiting_time;
+            fire_slow_reaction(system, &mut state, &slow, rng);
+            slow_integral = 0.0;
+            slow_threshold = sample_threshold(rng);
+        } else {
+            let dt = settings.time_step.min(settings.end_time - time);
+            let new_state = fast_step(system, &state, &fast, dt);
+            let new_slow_propensity: f64 = slow.iter().map(|reaction| system.get_propensity(*reaction, &new_state)).sum();
+            let step_integral = 0.5 * dt * (slow_propensity + new_slow_propensity);
+
+            if slow_integral + step_integral >= slow_threshold {
+                // a slow reaction fires within this step, advance only to the moment it fires then fire it
+                let fraction = (slow_threshold - slow_integral) / step_integral;
+                state = fast_step(system, &state, &fast, fraction * dt);
+                time += fraction * dt;
+                fire_slow_reaction(system, &mut state, &slow, rng);
+                slow_integral = 0.0;
+                slow_threshold = sample_threshold(rng);
+            } else {
+                state = new_state;
+                time = if settings.end_time - time <= dt { settings.end_time } else { time + dt };
+                slow_integral += step_integral;
+            }
+        }
+
+        if settings.record_trajectory {
+            trajectory.push(time, state.clone());
+        }
+    }
+
+    if !settings.record_trajectory || trajectory.get_times().last() != Some(&time) {
+        trajectory.push(time, state);
+    }
+
+    return trajectory;
+}
+
+/// Splits reactions into fast reactions integrated deterministically and slow reactions fired exactly
+pub fn partition(system: &MassActionSystem, state: &[f64], settings: &HybridSettings) -> (Vec<usize>, Vec<usize>) {
+    let mut fast = Vec::new();
+    let mut slow = Vec::new();
+
+    for reaction in 0..system.num_reactions() {
+        let propensity = system.get_propensity(reaction, state);
+        let frequent = propensity * settings.time_step >= settings.fast_firings;
+        let abundant = system.get_reactants(reaction).iter().map(|(index, _)| *index)
+            .chain(system.get_net_changes(reaction).iter().map(|(index, _)| *index))
+            .all(|index| state[index] >= settings.fast_population);
+
+        if frequent && abundant {
+            fast.push(reaction);
+        } else if propensity > 0.0 {
+            slow.push(reaction);
+        }
+    }
+
+    return (fast, slow);
+}
+
+/// Integrates only the fast reactions over `dt` with a classic fourth order Runge-Kutta step
+fn fast_step(system: &MassActionSystem, state: &[f64], fast: &[usize], dt: f64) -> Vec<f64> {
+    let derivatives = |state: &[f64]| {
+        let mut derivatives = vec![0.0; state.len()];
+        for reaction in fast {
+            let propensity = system.get_propensity(*reaction, state);
+            for (index, net_change) in system.get_net_changes(*reaction) {
+                derivatives[*index] += *net_change as f64 * propensity;
+            }
+        }
+        derivatives
+    };
+    let offset = |k: &[f64], scale: f64| -> Vec<f64> {
+        state.iter().zip(k).map(|(value, slope)| value + scale * slope).collect()
+    };
+
+    let k1 = derivatives(state);
+    let k2 = derivatives(&offset(&k1, 0.5 * dt));
+    let k3 = derivatives(&offset(&k2, 0.5 * dt));
+    let k4 = derivatives(&offset(&k3, dt));
+
+    return (0..state.len())
+        .map(|i| (state[i] + dt / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i])).max(0.0))
+        .collect();
+}
+
+/// Fires one slow reaction chosen with probability proportional to its propensity
+fn fire_slow_reaction<R: Rng>(system: &MassActionSystem, state: &mut [f64], slow: &[usize], rng: &mut R) {
+    let propensities: Vec<f64> = slow.iter().map(|reaction| system.get_propensity(*reaction, state)).collect();
+    let total: f64 = propensities.iter().sum();
+    if total <= 0.0 {
+        return;
+    }
+
+    let mut index = rng.gen_range(0.0..total);
+    let mut chosen = slow[slow.len() - 1];
+    for (reaction, propensity) in slow.iter().zip(propensities) {
+        if propensity > index {
+            chosen = *reaction;
+            break;
+        }
+        index -= propensity;
+    }
+
+    for (index, net_change) in system.get_net_changes(chosen) {
+        state[*index] = (state[*index] + *net_change as f64).max(0.0);
+    }
+}
+
+/// Draws the unit rate exponential threshold the slow propensity integral must reach before the next slow firing
+fn sample_threshold<R: Rng>(rng: &mut R) -> f64 {
+    let uniform: f64 = 1.0 - rng.gen::<f64>();
+    return -uniform.ln();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::reaction_network::parse_test_reactions;
+
+    fn signal_and_fuel() -> MassActionSystem {
+        // fuel => waste is fast and abundant while signal => product is slow and rare
+        let reactions = parse_test_reactions("fuel => waste, 1\nsignal => product, 1");
+        return MassActionSystem::from(&reactions);
+    }
+
+    #[test]
+    fn test_partition() {
+        let system = signal_and_fuel();
+        // species are ordered fuel, product, signal, waste
+        let state = vec![1_000_000.0, 0.0, 5.0, 1000.0];
+        let settings = HybridSettings{time_step: 0.01, ..HybridSettings::default()};
+
+        let (fast, slow) = partition(&system, &state, &settings);
+        assert_eq!(fast.len(), 1);
+        assert_eq!(slow.len(), 1);
+        assert_eq!(system.get_reactants(fast[0]), &[(0, 1)]);
+    }
+
+    #[test]
+    fn test_hybrid_conserves_signal() {
+        let system = signal_and_fuel();
+        let settings = HybridSettings{end_time: 2.0, time_step: 0.01, ..HybridSettings::default()};
+
+        let trajectory = simulate(&system, vec![1_000_000.0, 0.0, 5.0, 1000.0], &settings, &mut rand::thread_rng());
+        let final_state = trajectory.final_state();
+
+        // slow reactions fire whole molecules
+        assert_eq!(final_state[1].1 + final_state[2].1, 5.0);
+        assert_eq!(final_state[1].1.fract(), 0.0);
+        // fast reactions follow the deterministic decay
+        assert!((final_state[0].1 - 1_000_000.0 * (-2.0_f64).exp()).abs() < 1.0);
+    }
+}
@@ -0,0 +1,113 @@
+/// # Description
+/// Chemical Langevin equation simulation of a chemical reaction network.
+/// This is a continuous stochastic approximation sitting between exact stochastic simulation and the deterministic ODEs,
+/// suited to networks where species counts are too large for exact simulation but too small for noise to be ignored.
+///
+/// Every trial is integrated with the Euler-Maruyama method using a fixed time step `dt`
+/// `x(t + dt) = x(t) + sum_j v_j a_j(x) dt + sum_j v_j sqrt(a_j(x) dt) N_j`
+/// where `v_j` is the net stoichiometry of reaction `j`, `a_j` its propensity, and `N_j` an independent standard normal sample.
+/// Counts which a step would make negative are clamped to 0.
+
+use rand::Rng;
+use rand_distr::StandardNormal;
+use crate::ode::{MassActionSystem, OdeTrajectory};
+
+/// Settings for a chemical Langevin trial
+/// - `end_time`: the simulated time every trial is integrated until
+/// - `time_step`: the fixed Euler-Maruyama step size
+/// - `record_trajectory`: whether every step is recorded, otherwise only the initial and final states are kept
+#[derive(Debug, Clone, Copy)]
+pub struct LangevinSettings {
+    pub end_time: f64,
+    pub time_step: f64,
+    pub record_trajectory: bool,
+}
+
+impl Default for LangevinSettings {
+    fn default() -> Self {
+        Self {
+            end_time: 1.0,
+            time_step: 1e-3,
+            record_trajectory: false,
+        }
+    }
+}
+
+/// Integrates a single chemical Langevin trial from the given initial state
+pub fn simulate<R: Rng>(system: &MassActionSystem, initial_state: Vec<f64>, settings: &LangevinSettings, rng: &mut R) -> OdeTrajectory {
+    let mut trajectory = OdeTrajectory::new(system.get_species().to_vec());
+    let mut propensities = vec![0.0; system.num_reactions()];
+    let mut state = initial_state;
+    let mut time = 0.0;
+    trajectory.push(time, state.clone());
+
+    // step times are computed from the step number so rounding error does not accumulate
+    let num_steps = (settings.end_time / settings.time_step * (1.0 - 1e-12)).ceil() as usize;
+    for step in 1..=num_steps {
+        let next_time = (step as f64 * settings.time_step).min(settings.end_time);
+        let dt = next_time - time;
+        system.get_propensities(&state, &mut propensities);
+
+        let mut new_state = state.clone();
+        for (reaction, propensity) in propensities.iter().enumerate() {
+            if *propensity <= 0.0 {
+                continue;
+            }
+
+            let noise: f64 = rng.sample(StandardNormal);
+            let firings = propensity * dt + (propensity * dt).sqrt() * noise;
+            for (index, net_change) in system.get_net_changes(reaction) {
+                new_state[*index] += *net_change as f64 * firings;
+            }
+        }
+
+        // keep counts non negative
+        new_state.iter_mut().for_each(|count| *count = count.max(0.0));
+
+        state = new_state;
+        time = next_time;
+        if settings.record_trajectory {
+            trajectory.push(time, state.clone());
+        }
+    }
+
+    if !settings.record_trajectory {
+        trajectory.push(time, state);
+    }
+
+    return trajectory;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::reaction_network::parse_test_reactions;
+
+    #[test]
+    fn test_counts_stay_non_negative() {
+        // a step far too large for the population
+        let reactions = parse_test_reactions("A => B, 50");
+        let system = MassActionSystem::from(&reactions);
+        let settings = LangevinSettings{end_time: 1.0, time_step: 0.1, record_trajectory: true};
+
+        let trajectory = simulate(&system, vec![10.0, 0.0], &settings, &mut rand::thread_rng());
+        assert_eq!(trajectory.get_times().len(), 11);
+        assert!(trajectory.get_states().iter().flatten().all(|count| *count >= 0.0));
+    }
+
+    #[test]
+    fn test_mean_follows_decay() {
+        // averaged over many trials should follow A(0) * e^-t
+        let reactions = parse_test_reactions("A => B, 1");
+        let system = MassActionSystem::from(&reactions);
+        let settings = LangevinSettings{end_time: 1.0, time_step: 1e-3, record_trajectory: false};
+
+        let trials = 200;
+        let mut sum = 0.0;
+        for _ in 0..trials {
+            sum += simulate(&system, vec![1000.0, 0.0], &settings, &mut rand::thread_rng()).final_state()[0].1;
+        }
+        let mean = sum / trials as f64;
+        assert!((mean - 1000.0 * (-1.0_f64).exp()).abs() < 10.0);
+    }
+}
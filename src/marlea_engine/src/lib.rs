@@ -24,25 +24,57 @@
 ///     Specifies the maximum time the code my run for in seconds
 ///     Is of type Option which may be None 
 ///     - if None the simulation will run indefinitely
+/// - <max_semi_stable_steps>
+///     Specifies how many steps a trial may remain semi stable before it is considered stable
+///     Is of type Option which may be None
+/// - <algorithm>
+///     Specifies the stochastic simulation algorithm each trial uses
+///     Is of type Option which may be None
+///     - if None will default to the Gillespie direct method
+/// - <duplicates>
+///     Specifies whether reactions written more than once in the input file are kept, merged by summing their rates, or rejected
+///     Is of type Option which may be None
+///     - if None every repeated reaction is kept as a separate channel
+/// 
+/// `new()` panics if the input file has errors, `try_new()` instead returns a diagnostic for every problem in the file,
+/// pointing at the line and column it was found at. Warnings about files without errors are kept by the engine,
+/// `get_warnings()` returns them so they can be shown before running.
+/// 
+/// `set_precision_settings()` makes `run()` schedule trials until the confidence interval of the mean of every targeted species
+/// is narrow enough, or a cap is reached, instead of running a fixed number of trials.
+/// `set_seed()` fixes the master seed every trial derives its own random number stream from,
+/// making results reproducible no matter how trials are scheduled across threads.
+/// Without a seed a random master seed is drawn and printed so the run can be repeated.
+/// 
+/// Once constructed `run()` performs stochastic trials and returns a report of the statistics of their final solutions, `run_ode()` integrates the deterministic mass action ODEs
+/// `run_langevin()` simulates trials of the chemical Langevin equation, and `run_hybrid()` simulates trials
+/// which integrate fast reactions deterministically while firing slow reactions exactly, all for the same network.
+/// These return the resulting species values sorted alphabetically.
+/// `run_fsp()` instead returns the exact probability distribution of the network's outcomes by finite state projection.
 /// 
 /// Accepted file types: 
 ///     - CSV
 ///     - XML UNIMPLEMENTED! 
 ///     - JSON UNIMPLEMENTED!
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use rand::{SeedableRng, rngs::StdRng};
+use num_bigint::BigUint;
 
-use std::sync::mpsc::{
-    sync_channel,
-    SyncSender, Receiver,
-};
-use std::usize;
-use supported_file_type::SupportedFileType; 
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use supported_file_type::{SupportedFileType, TimelineWriter}; 
 use threadpool::ThreadPool;
 use trial::{
     results::TrialResult, 
     reaction_network::{
         ReactionNetwork, 
+        SimulationAlgorithm,
+        count::Count,
+        duplicates::DuplicatePolicy,
         reaction::{
             Reaction, 
             term::solution::{
@@ -53,10 +85,23 @@ use trial::{
     }
 };
 
-use self::supported_file_type::TimelineWriter; 
+use ode::{MassActionSystem, OdeSettings, OdeTrajectory};
+use langevin::LangevinSettings;
+use hybrid::HybridSettings;
+use fsp::{FspSettings, ProbabilityDistribution};
+use statistics::{EnsembleStatistics, ReportSettings, SimulationReport, precision::PrecisionSettings};
+use trial::threshold::Threshold;
+use parser::diagnostic::Diagnostic;
 
 
 pub mod trial;
+pub mod ode;
+pub mod langevin;
+pub mod hybrid;
+pub mod fsp;
+pub mod statistics;
+pub mod parser;
+pub mod validate;
 mod supported_file_type; 
 //mod tests;
 
@@ -67,11 +112,14 @@ pub struct MarleaEngine {
     num_trials: Option<usize>,
     max_runtime: Option<u64>,
     max_semi_stable_steps: Option<i32>,
+    report_settings: ReportSettings,
+    thresholds: Vec<Threshold>,
+    precision_settings: Option<PrecisionSettings>,
+    seed: Option<u64>,
 
     // constructed by struct
+    warnings: Vec<Diagnostic>,
     computation_threads: ThreadPool,
-    computations_threads_sender: SyncSender<TrialResult>,
-    computation_threads_reciever: Receiver<TrialResult>,
     prime_network: ReactionNetwork
 }
 
@@ -81,7 +129,6 @@ impl MarleaEngine {
         let computation_threads = threadpool::Builder::new()
             .thread_name("compute_thread".into())
             .build();
-        let computation_threads_channels = sync_channel(0);
 
         Self { 
             out_path: None,
@@ -89,13 +136,19 @@ impl MarleaEngine {
             num_trials: None, 
             max_runtime: None, 
             max_semi_stable_steps: None, 
-            computation_threads: computation_threads, 
-            computations_threads_sender: computation_threads_channels.0, 
-            computation_threads_reciever: computation_threads_channels.1, 
+            report_settings: ReportSettings::default(),
+            thresholds: Vec::new(),
+            precision_settings: None,
+            seed: None,
+            warnings: Vec::new(),
+            computation_threads, 
             prime_network: custom_network
         }
     }
 
+    /// Constructs an engine as `try_new()` does, printing every diagnostic and panicking if the input or init file has errors.
+    /// Warnings are printed as well
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         input_path: String,
         init_path: Option<String>,
@@ -104,130 +157,261 @@ impl MarleaEngine {
         num_trials: Option<usize>,
         max_runtime: Option<u64>,
         max_semi_stable_steps: Option<i32>,
-    ) -> Self { 
+        algorithm: Option<SimulationAlgorithm>,
+        duplicates: Option<DuplicatePolicy>,
+    ) -> Self {
+        let engine = Self::try_new(input_path, init_path, out_path, out_timeline, num_trials, max_runtime, max_semi_stable_steps, algorithm, duplicates)
+            .unwrap_or_else(|diagnostics| {
+                for diagnostic in &diagnostics {
+                    println!("{}", diagnostic);
+                }
+                let errors = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+                panic!("could not parse the input files, found {} errors", errors);
+            });
+        for warning in engine.get_warnings() {
+            println!("{}", warning);
+        }
+        return engine;
+    }
 
-        let reactions = SupportedFileType::from(input_path).parse_reactions();
-        let solution = Self::solution_from(init_path, &reactions);
-        let prime_network = ReactionNetwork::new(reactions, solution);
+    /// Constructs an engine from an input file and optional init file,
+    /// returning every diagnostic found if either file has any errors, warnings are kept for `get_warnings()`
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(
+        input_path: String,
+        init_path: Option<String>,
+        out_path: Option<String>,
+        out_timeline: Option<String>,
+        num_trials: Option<usize>,
+        max_runtime: Option<u64>,
+        max_semi_stable_steps: Option<i32>,
+        algorithm: Option<SimulationAlgorithm>,
+        duplicates: Option<DuplicatePolicy>,
+    ) -> Result<Self, Vec<Diagnostic>> { 
+
+        let (reactions, mut warnings) = SupportedFileType::from(input_path).parse_reactions(duplicates.unwrap_or_default())?;
+        let (solution, thresholds, init_warnings) = Self::solution_from(init_path, &reactions)?;
+        warnings.extend(init_warnings);
+        let mut prime_network = ReactionNetwork::new(reactions, solution);
+        prime_network.set_algorithm(algorithm.unwrap_or_default());
         let computation_threads = threadpool::Builder::new()
             .thread_name("compute_thread".into())
             .build();
-        let computation_threads_channels = sync_channel(0);
 
-        Self{
+        Ok(Self{
             out_path,
             out_timeline,
             num_trials,
             max_runtime,
             max_semi_stable_steps,
+            report_settings: ReportSettings::default(),
+            thresholds,
+            precision_settings: None,
+            seed: None,
+            warnings,
             computation_threads,
-            computations_threads_sender: computation_threads_channels.0,
-            computation_threads_reciever: computation_threads_channels.1,
             prime_network,
-        } 
+        })
     }
 
-    pub fn run(&self) -> Vec<(String, f64)> {
-        // set containing all trial results
-        let mut simulation_results = HashSet::new();
+    /// returns the warnings found in the input and init files, those of the input file first
+    pub fn get_warnings(&self) -> &[Diagnostic] {
+        return &self.warnings;
+    }
+
+    /// Sets the quantiles and histograms reported by `run()`
+    pub fn set_report_settings(&mut self, report_settings: ReportSettings) {
+        self.report_settings = report_settings;
+    }
+
+    /// Sets the thresholds which stop a trial as soon as any of them is reached
+    pub fn set_thresholds(&mut self, thresholds: Vec<Threshold>) {
+        self.thresholds = thresholds;
+    }
+
+    /// Sets confidence interval targets which `run()` schedules trials until are met, instead of running a fixed number of trials.
+    /// Targets on species which are not in the network are ignored, and a confidence level outside of (0, 1) is an error
+    pub fn set_precision_settings(&mut self, precision_settings: PrecisionSettings) -> Result<(), String> {
+        if !(precision_settings.confidence > 0.0 && precision_settings.confidence < 1.0) {
+            return Err(format!("Invalid confidence level {} - expected a value between 0 and 1", precision_settings.confidence));
+        }
+        let mut precision_settings = precision_settings;
+        precision_settings.targets.retain(|target| {
+            let known = self.prime_network.get_network().get_species_index(&target.species).is_some();
+            if !known {
+                println!("WARNING: precision target for unknown species {} is ignored", target.species);
+            }
+            known
+        });
+        self.precision_settings = Some(precision_settings);
+        return Ok(());
+    }
+
+    /// Sets the master seed every trial derives its random number stream from
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    // returns the master seed for a run, drawing and printing a random one if no seed was set
+    fn get_master_seed(&self) -> u64 {
+        return match self.seed {
+            Some(seed) => seed,
+            None => {
+                let seed = rand::random();
+                println!("Using random seed {}", seed);
+                seed
+            }
+        };
+    }
+
+    /// Runs trials counting species with `u64`s, a trial which would overflow a count stops the run with an error naming the reaction
+    pub fn run(&self) -> Result<SimulationReport, String> {
+        return self.run_trials(self.prime_network.clone(), simulate_trial);
+    }
+
+    /// Runs trials counting species with arbitrary precision, for networks whose counts grow without bound.
+    /// Trials are slower but never overflow, reports are written exactly as by `run()`
+    pub fn run_big_counts(&self) -> Result<SimulationReport<BigUint>, String> {
+        return self.run_trials(self.prime_network.clone().with_count_type(), simulate_trial);
+    }
+
+    // runs every trial with `simulate` on the worker threads, a trial which panics fails the run like one which returns an error
+    fn run_trials<C: Count>(&self, prime_network: ReactionNetwork<C>, simulate: TrialSimulation<C>) -> Result<SimulationReport<C>, String> {
+        // streaming statistics of all trial results
+        let mut simulation_results = EnsembleStatistics::from(prime_network.get_species(), &self.thresholds);
+        let seed = self.get_master_seed();
+
+        // results are added to the statistics in trial id order so that they do not depend on thread scheduling,
+        // results which arrive early wait here until every trial before them has been recieved
+        let mut pending_results: BTreeMap<usize, (Vec<C>, Vec<usize>)> = BTreeMap::new();
+        let mut next_result = 0;
 
         // setup loop variables
         let mut trials_recieved = 0;
         let mut trials_created = 0;
-        let max_trials = match self.num_trials{Some(number) => number, None => 100};        
+        simulation_results.set_precision_settings(self.precision_settings.clone());
+        let max_trials = match &self.precision_settings {
+            Some(settings) => settings.max_trials,
+            None => self.num_trials.unwrap_or(100),
+        };
+        // trials are scheduled a few at a time so that no more are run than are needed to meet precision targets
+        let max_scheduled_trials = self.computation_threads.max_count() * 2;
+        let mut finished = max_trials == 0;
 
-        // setup timeline writer if one is needed
-        let (timeline_writer_sender, timeline_writer_reciever) = sync_channel(0);
-        if let Some(path) = &self.out_timeline {
-            let timeline_writer = TimelineWriter::new(SupportedFileType::from(path.clone()), timeline_writer_reciever);
-            self.computation_threads.execute(move|| timeline_writer.begin_listen());
-        }
-  
-        // start runtime timer
-        let (timer_sender, timer_reciever) = sync_channel(0);
-        if let Some(time) = self.max_runtime {
-            self.computation_threads.execute(move|| Self::engine_runtime_timer(time, timer_sender));
-        }
+        // every trial of this run stops at its next step once the maximum runtime is reached
+        let cancelled = Arc::new(AtomicBool::new(false));
+        // the first trial which fails cancels the run, its error is returned once every running trial has stopped
+        let mut failure = None;
 
-        // create trials 
-        match &self.out_timeline {
-            Some(_) => {
-                while trials_created < max_trials {
-                    let mut current_trial = trial::Trial::from(self.prime_network.clone(), self.max_semi_stable_steps, trials_created);
-                    let trial_sender = self.computations_threads_sender.clone();
-                    self.computation_threads.execute(move|| current_trial.simulate_with_timeline(trial_sender));
-                    trials_created += 1;
-                }
+        // every trial reports to the engine over a buffered channel, the engine blocks on it until
+        // a trial finishes or the maximum runtime is reached so it never spins while it waits
+        // the engine drops its own sender once it stops scheduling trials, so a trial which is somehow lost without
+        // reporting disconnects the channel instead of blocking the engine forever
+        let (trial_sender, trial_reciever) = channel();
+        let mut trial_sender = Some(trial_sender);
+        let deadline = self.max_runtime.map(|time| Instant::now() + Duration::from_secs(time));
+        let timeline_file = self.out_timeline.clone().map(SupportedFileType::from);
+
+        // schedule trials and wait for their results until enough trials have been added to the statistics,
+        // once the statistics are finished or the run is cancelled every running trial is stopped and waited on
+        while !(finished || cancelled.load(Ordering::Relaxed)) || trials_recieved < trials_created {
+            while !(finished || cancelled.load(Ordering::Relaxed)) && trials_created < max_trials && trials_created - trials_recieved < max_scheduled_trials {
+                let mut current_trial = trial::Trial::from(prime_network.clone(), self.max_semi_stable_steps, trials_created, seed);
+                current_trial.set_thresholds(self.thresholds.clone());
+                current_trial.set_cancellation_token(Arc::clone(&cancelled));
+                let trial_sender = match &trial_sender {
+                    Some(sender) => sender.clone(),
+                    None => break,
+                };
+                // timeline files are created here so a bad timeline path stops the run before any trial writes to it
+                let timeline = match &timeline_file {
+                    Some(file) => match TimelineWriter::new(file, trials_created, prime_network.get_species()) {
+                        Ok(timeline) => Some(timeline),
+                        Err(error) => {
+                            cancelled.store(true, Ordering::Relaxed);
+                            failure.get_or_insert(error);
+                            break;
+                        }
+                    },
+                    None => None,
+                };
+                self.computation_threads.execute(move|| {
+                    let id = current_trial.get_id();
+                    let failed_sender = trial_sender.clone();
+                    if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(move|| simulate(current_trial, trial_sender, timeline))) {
+                        let _ = failed_sender.send(TrialResult::Failed(panic_message(panic.as_ref()), id));
+                    }
+                });
+                trials_created += 1;
             }
-            None => {
-                while trials_created < max_trials {
-                    let mut current_trial = trial::Trial::from(self.prime_network.clone(), self.max_semi_stable_steps, trials_created);
-                    let trial_sender = self.computations_threads_sender.clone();
-                    self.computation_threads.execute(move || current_trial.simulate(trial_sender));                    
-                    trials_created += 1;
-                }
+            if finished || cancelled.load(Ordering::Relaxed) || trials_created >= max_trials {
+                trial_sender = None;
+            }
+            // a run which stops before scheduling a trial has nothing to wait on
+            if trials_recieved == trials_created {
+                continue;
             }
-        }
 
-        // poll for trial results
-        while trials_recieved < max_trials {
-            if let Ok(result) = self.computation_threads_reciever.try_recv() {
-                match result {
-                    TrialResult::StableSolution(solution, steps) => {
-                        trials_recieved += 1;
-                        println!("Trial stable after {} steps", steps);
-                        println!("Recieved {} trials", trials_recieved);
-                        simulation_results.insert(solution);
+            let result = match deadline {
+                Some(deadline) if !cancelled.load(Ordering::Relaxed) => trial_reciever.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                _ => trial_reciever.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match result {
+                Ok(TrialResult::StableSolution(counts, steps, simulated_time, reached_thresholds, id)) => {
+                    trials_recieved += 1;
+                    println!("Trial stable after {} steps at simulated time {}", steps, simulated_time);
+                    println!("Recieved {} trials", trials_recieved);
+                    pending_results.insert(id, (counts, reached_thresholds));
+                    while let Some((counts, reached_thresholds)) = pending_results.remove(&next_result) {
+                        if finished {
+                            break;
+                        }
+                        simulation_results.push(&counts, &reached_thresholds);
+                        next_result += 1;
+                        finished = next_result >= max_trials || simulation_results.is_precise();
                     }
-                    TrialResult::TimelineEntry(solution, id) => {
-                        timeline_writer_sender.send((solution, id)).unwrap();
+                    // trials still running once the statistics are finished would be discarded, so they are stopped early
+                    if finished {
+                        cancelled.store(true, Ordering::Relaxed);
                     }
                 }
-            }
-            
-            if let Ok(_) = timer_reciever.try_recv() {
-                println!("forced termination because max time was reached\n\nWARNING: returned results may not be accurate and should be used for debugging purposes only");
-                break;
+                Ok(TrialResult::Cancelled(_)) => {
+                    trials_recieved += 1;
+                }
+                Ok(TrialResult::Failed(error, id)) => {
+                    trials_recieved += 1;
+                    cancelled.store(true, Ordering::Relaxed);
+                    failure.get_or_insert(format!("Trial {} failed: {}", id, error));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    println!("forced termination because max time was reached, stopping running trials");
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(format!("{} of {} trials stopped without reporting a result", trials_created - trials_recieved, trials_created));
+                }
             }
         }
 
-        drop(timeline_writer_sender);
-
-        return self.terminate(simulation_results);
+        if let Some(error) = failure {
+            return Err(error);
+        }
 
-    }
-    
-    fn average_trials(simulation_results: HashSet<Solution>) -> Vec<(String, f64)> {
-        let mut summed_values = HashMap::<String, f64>::new();
-        let num_trials = simulation_results.len() as f64;
-    
-        // Sum values of each species across all trials
-        for result in &simulation_results {
-            for (name, count) in result.species_counts.clone() {
-                if let Species::Name(species_name) = name {
-                    if let Species::Count(species_count) = count  {
-                        summed_values.entry(species_name)
-                        .and_modify(|summed_count| *summed_count +=  species_count as f64)
-                        .or_insert(species_count as f64);    
-                    }
-                } else {
-                    panic!("Got non-species name when calculating averages");
-                }
+        // a forced termination may leave gaps, the remaining results are still added in id order
+        if !finished {
+            for (counts, reached_thresholds) in pending_results.values() {
+                simulation_results.push(counts, reached_thresholds);
+            }
+            if cancelled.load(Ordering::Relaxed) {
+                simulation_results.mark_incomplete();
             }
         }
-    
-        // Calculate averages and sort alphabetically
-        let mut averaged_values: Vec<(String, f64)> = summed_values
-                        .into_iter()
-                        .map(|(key, value)| (key, value / num_trials))
-                        .collect();
-        averaged_values.sort_by_key(|(species, _)| species.to_owned());
-
-        return averaged_values;
+
+        return self.terminate(simulation_results);
+
     }
     
-
-    fn solution_from(file_path: Option<String>, reactions: &HashSet<Reaction>) -> Solution {
+    fn solution_from(file_path: Option<String>, reactions: &[Reaction]) -> Result<(Solution, Vec<Threshold>, Vec<Diagnostic>), Vec<Diagnostic>> {
         let mut species_counts: HashMap<Species, Species> = HashMap::new();
 
         // Get possible species from reactions
@@ -242,35 +426,346 @@ impl MarleaEngine {
             }
         }
 
+        let mut thresholds = Vec::new();
+        let mut warnings = Vec::new();
         if let Some(path) = file_path {
-            SupportedFileType::from(path).parse_initial_solution(&mut species_counts);
+            (thresholds, warnings) = SupportedFileType::from(path).parse_initial_solution(&mut species_counts)?;
         }
 
-        return Solution{species_counts}; 
+        return Ok((Solution{species_counts}, thresholds, warnings)); 
     }
 
-    fn terminate(&self, simulation_results: HashSet<Solution>) -> Vec<(String, f64)> {
+    fn terminate<C: Count>(&self, simulation_results: EnsembleStatistics<C>) -> Result<SimulationReport<C>, String> {
         
-        let average_stable_solution = Self::average_trials(simulation_results);
+        let report = simulation_results.report();
+        self.write_report(&report)?;
+
+        return Ok(report);
+    }
+
+    /// Integrates the mass action ODEs of the network from its initial solution.
+    /// The concentration trajectory is written to the timeline file if one was provided,
+    /// and the final state is written to the output file or printed just like the results of `run()`.
+    /// Returns an error if the trajectory or final state cannot be written to the requested file type
+    pub fn run_ode(&self, settings: OdeSettings) -> Result<Vec<(String, f64)>, String> {
+        self.warn_ignored_delays();
+        let system = MassActionSystem::new(Arc::clone(self.prime_network.get_network()));
+        let initial_state = system.initial_state(self.prime_network.get_counts());
+        let trajectory = ode::integrate(&system, initial_state, &settings);
 
+        println!("ODE integration finished after {} steps at time {}", trajectory.get_times().len() - 1, trajectory.get_times().last().copied().unwrap_or(0.0));
+
+        if let Some(path) = &self.out_timeline {
+            SupportedFileType::from(path.clone()).write_trajectory(&trajectory)?;
+        }
+
+        let final_state = trajectory.final_state();
+        self.write_results(&final_state)?;
+
+        return Ok(final_state);
+    }
+
+    /// Simulates trials of the network with the chemical Langevin equation.
+    /// Every trial's trajectory is written to its own timeline file if a timeline was requested,
+    /// and the averaged final state of all trials is written and returned just like the results of `run()`.
+    pub fn run_langevin(&self, settings: LangevinSettings) -> Result<Vec<(String, f64)>, String> {
+        let settings = LangevinSettings{record_trajectory: self.out_timeline.is_some(), ..settings};
+        return self.run_continuous_trials(move |system, initial_state, rng| {
+            langevin::simulate(system, initial_state, &settings, rng)
+        });
+    }
+
+    /// Simulates trials of the network with the hybrid SSA/ODE simulator,
+    /// where reactions involving only abundant species are integrated deterministically and all others are fired exactly.
+    /// Trajectories and results are handled exactly as in `run_langevin()`.
+    pub fn run_hybrid(&self, settings: HybridSettings) -> Result<Vec<(String, f64)>, String> {
+        let settings = HybridSettings{record_trajectory: self.out_timeline.is_some(), ..settings};
+        return self.run_continuous_trials(move |system, initial_state, rng| {
+            hybrid::simulate(system, initial_state, &settings, rng)
+        });
+    }
+
+    // Runs `num_trials` trials of a simulator with a continuous state on the computation threads
+    // and averages their final states, each trial draws from its own seeded random number generator
+    fn run_continuous_trials<F>(&self, simulate: F) -> Result<Vec<(String, f64)>, String> 
+    where F: Fn(&MassActionSystem, Vec<f64>, &mut StdRng) -> OdeTrajectory + Send + Sync + 'static {
+        self.warn_ignored_delays();
+        let system = Arc::new(MassActionSystem::new(Arc::clone(self.prime_network.get_network())));
+        let initial_state = system.initial_state(self.prime_network.get_counts());
+        let max_trials = self.num_trials.unwrap_or(100);
+        let simulate = Arc::new(simulate);
+        let seed = self.get_master_seed();
+
+        // set once a trial fails, so trials which have not started yet are skipped instead of simulated for nothing
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (trial_sender, trial_reciever) = channel();
+        for id in 0..max_trials {
+            let system = Arc::clone(&system);
+            let simulate = Arc::clone(&simulate);
+            let initial_state = initial_state.clone();
+            let trial_sender = trial_sender.clone();
+            let cancelled = Arc::clone(&cancelled);
+            self.computation_threads.execute(move|| {
+                if cancelled.load(Ordering::SeqCst) {
+                    return;
+                }
+                let mut rng = StdRng::seed_from_u64(trial::trial_seed(seed, id));
+                let trajectory = panic::catch_unwind(AssertUnwindSafe(|| simulate(&system, initial_state, &mut rng)))
+                    .map_err(|panic| panic_message(panic.as_ref()));
+                // the engine only stops listening once a trial has failed, so a lost result no longer matters
+                let _ = trial_sender.send((id, trajectory));
+            });
+        }
+        drop(trial_sender);
+
+        // final states are summed in trial id order so the average does not depend on thread scheduling
+        let mut final_states: Vec<Option<Vec<(String, f64)>>> = vec![None; max_trials];
+        let mut trials_recieved = 0;
+        let mut failure = None;
+        for (id, trajectory) in trial_reciever.iter() {
+            trials_recieved += 1;
+            println!("Recieved {} trials", trials_recieved);
+
+            let written = trajectory
+                .map_err(|error| format!("Trial {} failed: {}", id, error))
+                .and_then(|trajectory| {
+                    if let Some(path) = &self.out_timeline {
+                        SupportedFileType::from(format!("{}{}", id, path)).write_trajectory(&trajectory)?;
+                    }
+                    return Ok(trajectory.final_state());
+                });
+            match written {
+                Ok(final_state) => final_states[id] = Some(final_state),
+                Err(error) => {
+                    failure = Some(error);
+                    break;
+                }
+            }
+        }
+
+        // stop the remaining trials and wait for the running ones, so no worker outlives a failed run
+        if let Some(error) = failure {
+            cancelled.store(true, Ordering::SeqCst);
+            drop(trial_reciever);
+            self.computation_threads.join();
+            return Err(error);
+        }
+        let final_states = final_states.into_iter().collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("{} of {} trials stopped without reporting a result", max_trials - trials_recieved, max_trials))?;
+
+        let mut summed_values = vec![0.0; system.get_species().len()];
+        for final_state in &final_states {
+            for (sum, (_, count)) in summed_values.iter_mut().zip(final_state) {
+                *sum += count;
+            }
+        }
+
+        let averaged_values: Vec<(String, f64)> = system.get_species().iter().cloned()
+            .zip(summed_values.into_iter().map(|sum| sum / max_trials.max(1) as f64))
+            .collect();
+        self.write_results(&averaged_values)?;
+
+        return Ok(averaged_values);
+    }
+
+    /// Computes the probability distribution of the network by finite state projection of the chemical master equation,
+    /// either over final outcomes or at the end time given in `settings`.
+    /// The distribution is written to the output file if one was given, otherwise it is printed to the console.
+    /// Returns an error if the distribution cannot be written to the requested file type
+    pub fn run_fsp(&self, settings: FspSettings) -> Result<ProbabilityDistribution, String> {
+        self.warn_ignored_delays();
+        let system = MassActionSystem::new(Arc::clone(self.prime_network.get_network()));
+        let initial_state = self.prime_network.get_counts().to_vec();
+
+        let distribution = fsp::solve(&system, initial_state, &settings);
+        println!("Enumerated {} states", distribution.num_states());
+
+        if let Some(path) = &self.out_path {
+            SupportedFileType::from(path.clone()).write_distribution(&distribution)?;
+        } else {
+            println!("{},probability", distribution.get_species().join(","));
+            for (state, probability) in distribution.get_outcomes() {
+                let counts: Vec<String> = state.iter().map(|count| count.to_string()).collect();
+                println!("{},{}", counts.join(","), probability);
+            }
+        }
+
+        println!("Truncation error: {}", distribution.get_truncation_error());
+        if distribution.get_unresolved_probability() > 0.0 {
+            println!("Unresolved probability: {}", distribution.get_unresolved_probability());
+        }
+
+        return Ok(distribution);
+    }
+
+    // Only stochastic simulation supports delayed reactions, continuous simulations release products immediately
+    fn warn_ignored_delays(&self) {
+        if self.prime_network.get_reactions().iter().any(|reaction| reaction.get_delay().is_some()) {
+            println!("WARNING: reaction delays are only supported by the simulate query, delayed products will be released immediately");
+        }
+    }
+
+    fn write_report<C: Count>(&self, report: &SimulationReport<C>) -> Result<(), String> {
+        let quantiles = &self.report_settings.quantiles;
+
+        //write the report if output option ennabled
+        if let Some(path) = &self.out_path {
+            let output_file = SupportedFileType::from(path.clone());
+            output_file.write_report(report, quantiles)?;
+        } else {
+            let mut header = String::from("species,mean,variance,standard_error,min,max");
+            for level in quantiles {
+                header.push_str(&format!(",q{}", level));
+            }
+            println!("{}", header);
+
+            for ((name, statistics), distribution) in report.get_species().iter().zip(report.get_distributions()) {
+                let mut row = format!("{},{},{},{},{},{}", name, statistics.get_mean(), statistics.get_variance(), 
+                    statistics.get_standard_error(), statistics.get_min(), statistics.get_max());
+                for level in quantiles {
+                    row.push_str(&format!(",{}", distribution.get_quantile(*level)));
+                }
+                println!("{}", row);
+            }
+        }
+        println!("{} distinct outcomes across {} trials", report.get_outcomes().len(), report.get_num_trials());
+        if !report.is_complete() {
+            println!("WARNING: the maximum runtime was reached after {} trials finished, returned results are incomplete and may not be accurate", report.get_num_trials());
+        }
+        if let Some(confidence) = report.get_confidence() {
+            if report.precision_targets_met() {
+                println!("Precision targets met after {} trials at {}% confidence", report.get_num_trials(), confidence * 100.0);
+            } else {
+                println!("WARNING: precision targets were not met after {} trials at {}% confidence", report.get_num_trials(), confidence * 100.0);
+            }
+            println!("species,target_half_width,half_width");
+            for result in report.get_precision() {
+                println!("{},{},{}", result.species, result.target, result.half_width);
+            }
+        }
+        if !report.get_thresholds().is_empty() {
+            println!("threshold,trials_reached,probability");
+            for (index, (threshold, trials_reached)) in report.get_thresholds().iter().enumerate() {
+                println!("{},{},{}", threshold, trials_reached, report.get_threshold_probability(index));
+            }
+        }
+
+        // write histograms if histogram option ennabled
+        if let Some(path) = &self.report_settings.histogram_path {
+            SupportedFileType::from(path.clone()).write_histograms(report, &self.report_settings)?;
+        }
+        return Ok(());
+    }
+
+    fn write_results(&self, results: &[(String, f64)]) -> Result<(), String> {
         //write results if output option ennabled
         if let Some(path) = &self.out_path {
             let output_file = SupportedFileType::from(path.clone());
-            output_file.write_solution(average_stable_solution.clone());
+            output_file.write_solution(results.to_vec())?;
         } else {
-            for entry in average_stable_solution.clone() {
+            for entry in results {
                 println!("{},{}", entry.0 , entry.1);
             }
         }
+        return Ok(());
+    }
+
+}
+
+// simulates a single trial on a worker thread, writing its timeline if it has one
+type TrialSimulation<C> = fn(trial::Trial<C>, Sender<TrialResult<C>>, Option<TimelineWriter<C>>);
+
+fn simulate_trial<C: Count>(mut trial: trial::Trial<C>, trial_sender: Sender<TrialResult<C>>, timeline: Option<TimelineWriter<C>>) {
+    match timeline {
+        Some(timeline) => trial.simulate_with_timeline(trial_sender, timeline),
+        None => trial.simulate(trial_sender),
+    }
+}
+
+// describes the payload of a panic caught on a worker thread
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    return match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => format!("panicked: {}", message),
+        (_, Some(message)) => format!("panicked: {}", message),
+        _ => String::from("panicked"),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use trial::reaction_network::parse_test_network;
+    use statistics::precision::PrecisionTarget;
+    use std::sync::atomic::AtomicUsize;
+
+    fn test_engine() -> MarleaEngine {
+        // stable after a single step
+        let mut engine = MarleaEngine::custom_block(parse_test_network("A => B, 1", "A, 1"));
+        engine.set_seed(1);
+        return engine;
+    }
+
+    fn panic_on_trial_3(trial: trial::Trial, trial_sender: Sender<TrialResult>, timeline: Option<TimelineWriter>) {
+        if trial.get_id() == 3 {
+            panic!("trial 3 was lost");
+        }
+        simulate_trial(trial, trial_sender, timeline);
+    }
+
+    #[test]
+    fn test_panicked_trial_fails_run() {
+        // more trials than are ever scheduled at once, so some are still waiting to be scheduled when trial 3 panics
+        let mut engine = test_engine();
+        engine.num_trials = Some(engine.computation_threads.max_count() * 2 + 8);
+
+        let result = engine.run_trials(engine.prime_network.clone(), panic_on_trial_3);
+        assert_eq!(result.err(), Some(String::from("Trial 3 failed: panicked: trial 3 was lost")));
+    }
+
+    #[test]
+    fn test_unsupported_timeline_fails_run() {
+        let mut engine = test_engine();
+        engine.out_timeline = Some(String::from("timeline.json"));
+
+        assert_eq!(engine.run().err(), Some(String::from("unsupported timeline format for timeline.json, expected a CSV file")));
+    }
+
+    #[test]
+    fn test_invalid_confidence_is_rejected() {
+        let mut engine = test_engine();
+        let result = engine.set_precision_settings(PrecisionSettings{
+            targets: vec![PrecisionTarget{species: String::from("B"), half_width: 0.5}],
+            confidence: 95.0,
+            ..PrecisionSettings::default()
+        });
+        assert_eq!(result.err(), Some(String::from("Invalid confidence level 95 - expected a value between 0 and 1")));
+        assert!(engine.precision_settings.is_none());
+    }
+
+    #[test]
+    fn test_panicked_continuous_trial_fails_run() {
+        // more trials than are ever scheduled at once, so some have not started when the third one panics
+        let mut engine = test_engine();
+        engine.num_trials = Some(engine.computation_threads.max_count() * 2 + 8);
+        let trials_started = Arc::new(AtomicUsize::new(0));
 
-        return average_stable_solution;
+        let settings = LangevinSettings::default();
+        let result = engine.run_continuous_trials(move |system, initial_state, rng| {
+            if trials_started.fetch_add(1, Ordering::SeqCst) == 2 {
+                panic!("trial was lost");
+            }
+            return langevin::simulate(system, initial_state, &settings, rng);
+        });
+        let error = result.err().unwrap();
+        assert!(error.starts_with("Trial ") && error.ends_with(" failed: panicked: trial was lost"), "{}", error);
     }
 
-    fn engine_runtime_timer(runtime: u64, tx: SyncSender<bool>) {
-        let max_runtime = std::time::Duration::from_secs(runtime);
-        std::thread::sleep(max_runtime);
-        tx.send(true).unwrap();
-        return;
-    } 
+    #[test]
+    fn test_unwritable_trajectory_fails_run() {
+        let mut engine = test_engine();
+        engine.out_timeline = Some(String::from("missing_directory/timeline.csv"));
 
-}
\ No newline at end of file
+        let error = engine.run_ode(OdeSettings::default()).err().unwrap();
+        assert!(error.starts_with("could not write missing_directory/timeline.csv: "), "{}", error);
+    }
+}
@@ -0,0 +1,380 @@
+/// # Description
+/// Deterministic simulation of a chemical reaction network.
+/// The parsed reactions are turned into a system of mass action ordinary differential equations
+/// which are then integrated with an adaptive Runge-Kutta (Dormand-Prince RK45) solver,
+/// or for stiff systems such as DNA strand displacement networks an implicit Rosenbrock solver
+/// using the analytic Jacobian of the mass action rate laws.
+///
+/// The rate law used for a reaction is `rate * x^n / n!` multiplied over all reactants,
+/// which is the large count limit of the stochastic propensity `rate * C(x, n)`.
+/// This keeps concentrations in the same units as the species counts used by the stochastic trials
+/// so that ODE limits may be compared directly against averaged stochastic results.
+
+use std::sync::Arc;
+use crate::trial::reaction_network::{compiled::CompiledNetwork, reaction::Reaction};
+
+pub mod rk45;
+pub mod rosenbrock;
+
+/// A compiled reaction network viewed as a system of mass action ODEs over its alphabetically ordered list of species
+#[derive(Debug, Clone)]
+pub struct MassActionSystem {
+    network: Arc<CompiledNetwork>,
+}
+
+impl MassActionSystem {
+
+    /// shares the compiled network of a reaction network
+    pub fn new(network: Arc<CompiledNetwork>) -> Self {
+        return Self{network};
+    }
+
+    /// compiles reactions, keeping the order they are given in
+    pub fn from(reactions: &[Reaction]) -> Self {
+        return Self::new(Arc::new(CompiledNetwork::from(reactions)));
+    }
+
+    /// returns the names of all species in the order used by state vectors
+    pub fn get_species(&self) -> &[String] {
+        return self.network.get_species();
+    }
+
+    /// returns the number of reactions in the system
+    pub fn num_reactions(&self) -> usize {
+        return self.network.get_reactions().len();
+    }
+
+    /// builds a state vector from species counts in the order of `get_species()`
+    pub fn initial_state(&self, counts: &[u64]) -> Vec<f64> {
+        return counts.iter().map(|count| *count as f64).collect();
+    }
+
+    /// writes the rate (flux) of every reaction at the given state into `fluxes`
+    pub fn get_fluxes(&self, state: &[f64], fluxes: &mut [f64]) {
+        for (reaction, flux) in self.network.get_reactions().iter().zip(fluxes.iter_mut()) {
+            let mut rate = reaction.get_rate();
+            for (index, coefficient) in reaction.get_reactants() {
+                rate *= mass_action_term(state[*index], *coefficient);
+            }
+            *flux = rate;
+        }
+    }
+
+    /// writes the stochastic propensity of every reaction at the given state into `propensities`
+    pub fn get_propensities(&self, state: &[f64], propensities: &mut [f64]) {
+        for (reaction, propensity) in propensities.iter_mut().enumerate() {
+            *propensity = self.get_propensity(reaction, state);
+        }
+    }
+
+    /// returns the stochastic propensity of a reaction at the given state,
+    /// this is `rate * C(x, n)` extended to real valued counts through the falling factorial and is never negative
+    pub fn get_propensity(&self, reaction: usize, state: &[f64]) -> f64 {
+        let reaction = self.network.get_reaction(reaction);
+        let mut propensity = reaction.get_rate();
+        for (index, coefficient) in reaction.get_reactants() {
+            let mut combinations = 1.0;
+            for i in 0..*coefficient {
+                combinations *= (state[*index] - i as f64).max(0.0) / (i + 1) as f64;
+            }
+            propensity *= combinations;
+        }
+        return propensity;
+    }
+
+    /// returns the index and coefficient of each reactant of a reaction
+    pub fn get_reactants(&self, reaction: usize) -> &[(usize, u64)] {
+        return self.network.get_reaction(reaction).get_reactants();
+    }
+
+    /// returns the index and net stoichiometric change of each species altered by a reaction
+    pub fn get_net_changes(&self, reaction: usize) -> &[(usize, i64)] {
+        return self.network.get_reaction(reaction).get_net_changes();
+    }
+
+    /// writes the Jacobian `d(dx_i/dt)/dx_k` at the given state into `jacobian`,
+    /// this is derived analytically from the stoichiometry and mass action rate law of every reaction
+    pub fn get_jacobian(&self, state: &[f64], jacobian: &mut [Vec<f64>]) {
+        jacobian.iter_mut().for_each(|row| row.iter_mut().for_each(|entry| *entry = 0.0));
+
+        for reaction in self.network.get_reactions() {
+            for (k, (species, coefficient)) in reaction.get_reactants().iter().enumerate() {
+                if *coefficient == 0 {
+                    continue;
+                }
+                // partial derivative of the reaction flux with respect to one reactant
+                let mut partial = reaction.get_rate() * mass_action_term(state[*species], coefficient - 1);
+                for (m, (other_species, other_coefficient)) in reaction.get_reactants().iter().enumerate() {
+                    if m != k {
+                        partial *= mass_action_term(state[*other_species], *other_coefficient);
+                    }
+                }
+
+                for (index, net_change) in reaction.get_net_changes() {
+                    jacobian[*index][*species] += *net_change as f64 * partial;
+                }
+            }
+        }
+    }
+
+    /// writes the time derivative of every species at the given state into `derivatives`
+    pub fn get_derivatives(&self, state: &[f64], derivatives: &mut [f64]) {
+        derivatives.iter_mut().for_each(|derivative| *derivative = 0.0);
+
+        for reaction in self.network.get_reactions() {
+            let mut rate = reaction.get_rate();
+            for (index, coefficient) in reaction.get_reactants() {
+                rate *= mass_action_term(state[*index], *coefficient);
+            }
+            for (index, net_change) in reaction.get_net_changes() {
+                derivatives[*index] += *net_change as f64 * rate;
+            }
+        }
+    }
+}
+
+/// `x^n / n!`, negative concentrations produced by numerical error are treated as 0
+fn mass_action_term(concentration: f64, coefficient: u64) -> f64 {
+    let concentration = concentration.max(0.0);
+    let mut term = 1.0;
+    for i in 1..=coefficient {
+        term *= concentration / i as f64;
+    }
+    return term;
+}
+
+/// Selects the method used to integrate the ODEs
+/// - `Auto`: starts with RK45 and switches to the Rosenbrock solver for the remainder of the run once stiffness is detected
+/// - `RungeKutta45`: explicit adaptive Dormand-Prince, efficient for non stiff systems
+/// - `Rosenbrock`: linearly implicit adaptive Rosenbrock 2(3), efficient for stiff systems mixing very fast and very slow rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OdeSolver {
+    #[default]
+    Auto,
+    RungeKutta45,
+    Rosenbrock,
+}
+
+impl std::str::FromStr for OdeSolver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_ref() {
+            "auto" => Ok(OdeSolver::Auto),
+            "rk45" | "runge-kutta" => Ok(OdeSolver::RungeKutta45),
+            "rosenbrock" | "stiff" => Ok(OdeSolver::Rosenbrock),
+            _ => Err(format!("Invalid ODE solver '{}', expected one of \"auto\", \"rk45\", \"rosenbrock\"", s)),
+        }
+    }
+}
+
+/// Settings for a deterministic integration
+/// - `end_time`: the simulated time to integrate until, if None integration continues until a steady state is reached
+/// - `relative_tolerance` and `absolute_tolerance`: the local error tolerated on each accepted step
+/// - `steady_state_tolerance`: integration without an end time stops once every derivative is below this,
+///                             relative to the largest concentration
+/// - `max_steps`: the maximum number of accepted steps before integration is abandoned
+/// - `solver`: the integration method to use
+#[derive(Debug, Clone, Copy)]
+pub struct OdeSettings {
+    pub end_time: Option<f64>,
+    pub relative_tolerance: f64,
+    pub absolute_tolerance: f64,
+    pub steady_state_tolerance: f64,
+    pub max_steps: usize,
+    pub solver: OdeSolver,
+}
+
+impl Default for OdeSettings {
+    fn default() -> Self {
+        Self {
+            end_time: None,
+            relative_tolerance: 1e-6,
+            absolute_tolerance: 1e-9,
+            steady_state_tolerance: 1e-9,
+            max_steps: 1_000_000,
+            solver: OdeSolver::default(),
+        }
+    }
+}
+
+/// The concentration of every species at each accepted integration step
+#[derive(Debug, Clone)]
+pub struct OdeTrajectory {
+    species: Vec<String>,
+    times: Vec<f64>,
+    states: Vec<Vec<f64>>,
+}
+
+impl OdeTrajectory {
+
+    pub fn new(species: Vec<String>) -> Self {
+        return Self{species, times: Vec::new(), states: Vec::new()};
+    }
+
+    pub fn push(&mut self, time: f64, state: Vec<f64>) {
+        self.times.push(time);
+        self.states.push(state);
+    }
+
+    pub fn get_species(&self) -> &[String] {
+        return &self.species;
+    }
+
+    pub fn get_times(&self) -> &[f64] {
+        return &self.times;
+    }
+
+    pub fn get_states(&self) -> &[Vec<f64>] {
+        return &self.states;
+    }
+
+    /// returns the number of accepted integration steps recorded after the initial state
+    pub fn num_steps(&self) -> usize {
+        return self.times.len().saturating_sub(1);
+    }
+
+    /// returns the most recently recorded time and state
+    fn last(&self) -> (f64, Vec<f64>) {
+        return (
+            self.times.last().copied().unwrap_or(0.0),
+            self.states.last().cloned().unwrap_or_else(|| vec![0.0; self.species.len()]),
+        );
+    }
+
+    /// returns the last recorded concentration of every species sorted alphabetically
+    pub fn final_state(&self) -> Vec<(String, f64)> {
+        return match self.states.last() {
+            Some(state) => self.species.iter().cloned().zip(state.iter().copied()).collect(),
+            None => Vec::new(),
+        };
+    }
+}
+
+/// How an integrator stopped
+/// - `Finished`: the end time or a steady state was reached, or integration was abandoned
+/// - `Stiff`: the explicit solver detected stiffness and handed off so that an implicit solver may continue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrationOutcome {
+    Finished,
+    Stiff,
+}
+
+/// Integrates a mass action system from the given initial state using the solver selected in settings
+pub fn integrate(system: &MassActionSystem, initial_state: Vec<f64>, settings: &OdeSettings) -> OdeTrajectory {
+    let mut trajectory = OdeTrajectory::new(system.get_species().to_vec());
+    trajectory.push(0.0, initial_state);
+
+    match settings.solver {
+        OdeSolver::RungeKutta45 => {
+            rk45::integrate(system, &mut trajectory, settings, false);
+        }
+        OdeSolver::Rosenbrock => {
+            rosenbrock::integrate(system, &mut trajectory, settings);
+        }
+        OdeSolver::Auto => {
+            if rk45::integrate(system, &mut trajectory, settings, true) == IntegrationOutcome::Stiff {
+                println!("Stiffness detected at time {}, switching to the Rosenbrock solver", trajectory.last().0);
+                rosenbrock::integrate(system, &mut trajectory, settings);
+            }
+        }
+    }
+
+    return trajectory;
+}
+
+/// Weighted root mean square norm used for error control
+fn error_norm(error: &[f64], state: &[f64], new_state: &[f64], settings: &OdeSettings) -> f64 {
+    if error.is_empty() {
+        return 0.0;
+    }
+
+    let mut sum = 0.0;
+    for i in 0..error.len() {
+        let scale = settings.absolute_tolerance + settings.relative_tolerance * state[i].abs().max(new_state[i].abs());
+        sum += (error[i] / scale).powi(2);
+    }
+    return (sum / error.len() as f64).sqrt();
+}
+
+/// Returns true once every derivative is negligible relative to the state
+fn is_steady(state: &[f64], derivatives: &[f64], settings: &OdeSettings) -> bool {
+    let scale = state.iter().fold(1.0_f64, |max, value| max.max(value.abs()));
+    return derivatives.iter().all(|derivative| derivative.abs() <= settings.steady_state_tolerance * scale);
+}
+
+/// Hairer's heuristic for an initial step size
+fn initial_step(state: &[f64], derivatives: &[f64]) -> f64 {
+    let state_norm = state.iter().fold(0.0_f64, |max, value| max.max(value.abs()));
+    let derivative_norm = derivatives.iter().fold(0.0_f64, |max, value| max.max(value.abs()));
+
+    if state_norm < 1e-5 || derivative_norm < 1e-5 {
+        return 1e-6;
+    }
+    return 0.01 * state_norm / derivative_norm;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::reaction_network::parse_test_reactions;
+
+    #[test]
+    fn test_derivatives() {
+        let reactions = parse_test_reactions("2 A => B, 3");
+        let system = MassActionSystem::from(&reactions);
+        assert_eq!(system.get_species(), &[String::from("A"), String::from("B")]);
+
+        // flux = 3 * 4^2 / 2! = 24
+        let mut derivatives = vec![0.0; 2];
+        system.get_derivatives(&[4.0, 0.0], &mut derivatives);
+        assert_eq!(derivatives, vec![-48.0, 24.0]);
+    }
+
+    #[test]
+    fn test_exponential_decay() {
+        // integrated to t = 1 should leave A(0) * e^-1
+        let reactions = parse_test_reactions("A => B, 1");
+        let system = MassActionSystem::from(&reactions);
+        let settings = OdeSettings{end_time: Some(1.0), ..OdeSettings::default()};
+
+        for solver in [OdeSolver::RungeKutta45, OdeSolver::Rosenbrock] {
+            let settings = OdeSettings{solver, ..settings};
+            let trajectory = integrate(&system, vec![100.0, 0.0], &settings);
+            let final_state = trajectory.final_state();
+
+            assert_eq!(*trajectory.get_times().last().unwrap(), 1.0);
+            assert!((final_state[0].1 - 100.0 * (-1.0_f64).exp()).abs() < 1e-3);
+            assert!((final_state[0].1 + final_state[1].1 - 100.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_jacobian() {
+        let reactions = parse_test_reactions("A + 2 B => C, 2");
+        let system = MassActionSystem::from(&reactions);
+
+        // flux = 2 * a * b^2 / 2 = a * b^2, d/da = b^2 = 9, d/db = 2 a b = 12
+        let mut jacobian = vec![vec![0.0; 3]; 3];
+        system.get_jacobian(&[2.0, 3.0, 0.0], &mut jacobian);
+        assert_eq!(jacobian[0], vec![-9.0, -12.0, 0.0]);
+        assert_eq!(jacobian[1], vec![-18.0, -24.0, 0.0]);
+        assert_eq!(jacobian[2], vec![9.0, 12.0, 0.0]);
+    }
+
+    #[test]
+    fn test_stiff_system_switches_solver() {
+        // A fast reversible binding step coupled to a slow leak
+        let reactions = parse_test_reactions("A <=> B, 10000, 10000\nB => C, 1");
+        let system = MassActionSystem::from(&reactions);
+        let settings = OdeSettings{end_time: Some(10.0), ..OdeSettings::default()};
+
+        let automatic = integrate(&system, vec![100.0, 0.0, 0.0], &settings);
+        let explicit = integrate(&system, vec![100.0, 0.0, 0.0], &OdeSettings{solver: OdeSolver::RungeKutta45, ..settings});
+
+        // B leaks at half the rate of the total so C = 100 * (1 - e^(-t / 2))
+        let expected = 100.0 * (1.0 - (-5.0_f64).exp());
+        assert!((automatic.final_state()[2].1 - expected).abs() < 1e-2);
+        assert!(automatic.num_steps() * 10 < explicit.num_steps());
+    }
+}
@@ -0,0 +1,167 @@
+/// Adaptive explicit Runge-Kutta integration using the Dormand-Prince 5(4) pair.
+/// The fifth order solution is propagated and the embedded fourth order solution is used to estimate local error.
+/// The last stage is evaluated at the new state so it is reused as the first stage of the next step (FSAL).
+/// Stiffness is detected with Hairer's estimate of `h * |lambda|` from the last two stages,
+/// so that automatic solver selection may hand off to an implicit solver.
+
+use super::{IntegrationOutcome, MassActionSystem, OdeSettings, OdeTrajectory, error_norm, initial_step, is_steady};
+
+/// Butcher tableau, the stage times are not needed since mass action systems are autonomous
+const A: [[f64; 6]; 7] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+    [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+    [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0],
+];
+/// difference between the fifth and fourth order weights
+const E: [f64; 7] = [
+    71.0 / 57600.0, 0.0, -71.0 / 16695.0, 71.0 / 1920.0, -17253.0 / 339200.0, 22.0 / 525.0, -1.0 / 40.0,
+];
+
+/// Smallest and largest factors a step size may change by after a single step
+const MIN_FACTOR: f64 = 0.2;
+const MAX_FACTOR: f64 = 5.0;
+const SAFETY: f64 = 0.9;
+
+/// `h * |lambda|` beyond which a step is considered limited by stability rather than accuracy
+const STIFFNESS_BOUND: f64 = 3.25;
+/// Number of stiff steps before the system is considered stiff
+const STIFF_STEPS: usize = 15;
+/// Number of consecutive non stiff steps which reset the count of stiff steps
+const NON_STIFF_STEPS: usize = 6;
+
+/// The result of attempting a single step
+/// - `state`: the fifth order solution at the end of the step
+/// - `derivatives`: the derivatives at `state`, reused as the first stage of the next step
+/// - `error`: the estimated local error of each species
+/// - `stiffness`: Hairer's estimate of `h * |lambda|` for the dominant eigenvalue of the Jacobian
+pub struct Step {
+    pub state: Vec<f64>,
+    pub derivatives: Vec<f64>,
+    pub error: Vec<f64>,
+    pub stiffness: f64,
+}
+
+/// Attempts a single Dormand-Prince step of size `h` from `state` where `derivatives` are the derivatives at `state`
+pub fn step(system: &MassActionSystem, state: &[f64], derivatives: &[f64], h: f64) -> Step {
+    let n = state.len();
+    let mut stages: Vec<Vec<f64>> = Vec::with_capacity(7);
+    stages.push(derivatives.to_vec());
+
+    let mut stage_state = vec![0.0; n];
+    let mut sixth_stage_state = vec![0.0; n];
+    for row in A.iter().skip(1) {
+        // keep the input of the sixth stage for the stiffness estimate
+        if stages.len() == 6 {
+            sixth_stage_state.copy_from_slice(&stage_state);
+        }
+        for j in 0..n {
+            let mut increment = 0.0;
+            for (k, stage) in stages.iter().enumerate() {
+                increment += row[k] * stage[j];
+            }
+            stage_state[j] = state[j] + h * increment;
+        }
+
+        let mut stage = vec![0.0; n];
+        system.get_derivatives(&stage_state, &mut stage);
+        stages.push(stage);
+    }
+
+    // the seventh stage state is the fifth order solution
+    let new_state = stage_state;
+
+    let mut error = vec![0.0; n];
+    for (j, value) in error.iter_mut().enumerate() {
+        let mut sum = 0.0;
+        for (k, stage) in stages.iter().enumerate() {
+            sum += E[k] * stage[j];
+        }
+        *value = h * sum;
+    }
+
+    // h * |f(y7) - f(y6)| / |y7 - y6|
+    let mut stage_difference = 0.0;
+    let mut state_difference = 0.0;
+    for j in 0..n {
+        stage_difference += (stages[6][j] - stages[5][j]).powi(2);
+        state_difference += (new_state[j] - sixth_stage_state[j]).powi(2);
+    }
+    let stiffness = if state_difference > 0.0 { h * (stage_difference / state_difference).sqrt() } else { 0.0 };
+
+    let derivatives = stages.pop().expect("seven stages were evaluated");
+    return Step{state: new_state, derivatives, error, stiffness};
+}
+
+/// Returns the factor to scale the step size by after a step with the given error norm
+pub fn step_factor(error_norm: f64) -> f64 {
+    if error_norm == 0.0 {
+        return MAX_FACTOR;
+    }
+    return (SAFETY * error_norm.powf(-1.0 / 5.0)).clamp(MIN_FACTOR, MAX_FACTOR);
+}
+
+/// Continues integrating a mass action system with adaptive Dormand-Prince steps from the last recorded point of a trajectory,
+/// recording the state after every accepted step.
+/// If `detect_stiffness` is set integration stops early and reports `Stiff` once steps are limited by stability.
+pub fn integrate(system: &MassActionSystem, trajectory: &mut OdeTrajectory, settings: &OdeSettings, detect_stiffness: bool) -> IntegrationOutcome {
+    let end_time = settings.end_time.unwrap_or(f64::INFINITY);
+
+    let (mut time, mut state) = trajectory.last();
+    let mut derivatives = vec![0.0; state.len()];
+    system.get_derivatives(&state, &mut derivatives);
+
+    let mut h = initial_step(&state, &derivatives);
+    let mut stiff_steps = 0;
+    let mut non_stiff_steps = 0;
+
+    while time < end_time {
+        if settings.end_time.is_none() && is_steady(&state, &derivatives, settings) {
+            break;
+        }
+        if trajectory.num_steps() >= settings.max_steps {
+            println!("WARNING: ODE integration stopped after {} steps at time {} without reaching its end", trajectory.num_steps(), time);
+            break;
+        }
+
+        h = h.min(end_time - time);
+        let attempt = step(system, &state, &derivatives, h);
+        let norm = error_norm(&attempt.error, &state, &attempt.state, settings);
+
+        if norm <= 1.0 {
+            time = if end_time - time <= h { end_time } else { time + h };
+            state = attempt.state;
+            derivatives = attempt.derivatives;
+            trajectory.push(time, state.clone());
+            h *= step_factor(norm);
+
+            if attempt.stiffness > STIFFNESS_BOUND {
+                stiff_steps += 1;
+                non_stiff_steps = 0;
+            } else {
+                non_stiff_steps += 1;
+                if non_stiff_steps >= NON_STIFF_STEPS {
+                    stiff_steps = 0;
+                }
+            }
+            if detect_stiffness && stiff_steps >= STIFF_STEPS {
+                return IntegrationOutcome::Stiff;
+            }
+        } else {
+            h *= step_factor(norm).min(1.0);
+        }
+
+        if time < end_time && h <= f64::EPSILON * time.abs().max(1.0) {
+            if detect_stiffness {
+                return IntegrationOutcome::Stiff;
+            }
+            println!("WARNING: ODE step size underflow at time {}, the system may be stiff", time);
+            break;
+        }
+    }
+
+    return IntegrationOutcome::Finished;
+}
@@ -0,0 +1,207 @@
+/// Adaptive linearly implicit Rosenbrock 2(3) integration, the method used by MATLAB's ode23s (Shampine and Reichelt 1997).
+/// Every step solves linear systems with the matrix `W = I - h * d * J` where `J` is the analytic Jacobian of the mass action system,
+/// which keeps the method stable for stiff systems where an explicit method would be forced into tiny steps.
+/// The second order solution is propagated and a third order stage is used to estimate local error.
+
+use super::{IntegrationOutcome, MassActionSystem, OdeSettings, OdeTrajectory, error_norm, initial_step, is_steady};
+
+/// Smallest and largest factors a step size may change by after a single step
+const MIN_FACTOR: f64 = 0.2;
+const MAX_FACTOR: f64 = 5.0;
+const SAFETY: f64 = 0.9;
+
+/// The result of attempting a single step
+/// - `state`: the second order solution at the end of the step
+/// - `derivatives`: the derivatives at `state`
+/// - `error`: the estimated local error of each species
+pub struct Step {
+    pub state: Vec<f64>,
+    pub derivatives: Vec<f64>,
+    pub error: Vec<f64>,
+}
+
+/// Attempts a single Rosenbrock step of size `h` from `state` where `derivatives` are the derivatives at `state`.
+/// Returns None if the step size makes `W` singular.
+pub fn step(system: &MassActionSystem, state: &[f64], derivatives: &[f64], h: f64) -> Option<Step> {
+    let n = state.len();
+    let d = 1.0 / (2.0 + std::f64::consts::SQRT_2);
+    let e32 = 6.0 + std::f64::consts::SQRT_2;
+
+    // W = I - h * d * J
+    let mut w = vec![vec![0.0; n]; n];
+    system.get_jacobian(state, &mut w);
+    for (i, row) in w.iter_mut().enumerate() {
+        for entry in row.iter_mut() {
+            *entry *= -h * d;
+        }
+        row[i] += 1.0;
+    }
+    let w = LuDecomposition::from(w)?;
+
+    // k1 = W^-1 f(y)
+    let k1 = w.solve(derivatives.to_vec());
+
+    // k2 = W^-1 (f(y + h/2 k1) - k1) + k1
+    let midpoint: Vec<f64> = (0..n).map(|i| state[i] + 0.5 * h * k1[i]).collect();
+    let mut f1 = vec![0.0; n];
+    system.get_derivatives(&midpoint, &mut f1);
+    let mut k2 = w.solve((0..n).map(|i| f1[i] - k1[i]).collect());
+    for i in 0..n {
+        k2[i] += k1[i];
+    }
+
+    let new_state: Vec<f64> = (0..n).map(|i| state[i] + h * k2[i]).collect();
+
+    // k3 = W^-1 (f(y_new) - e32 (k2 - f1) - 2 (k1 - f0))
+    let mut f2 = vec![0.0; n];
+    system.get_derivatives(&new_state, &mut f2);
+    let k3 = w.solve((0..n).map(|i| f2[i] - e32 * (k2[i] - f1[i]) - 2.0 * (k1[i] - derivatives[i])).collect());
+
+    let error = (0..n).map(|i| h / 6.0 * (k1[i] - 2.0 * k2[i] + k3[i])).collect();
+
+    return Some(Step{state: new_state, derivatives: f2, error});
+}
+
+/// Returns the factor to scale the step size by after a step with the given error norm
+pub fn step_factor(error_norm: f64) -> f64 {
+    if error_norm == 0.0 {
+        return MAX_FACTOR;
+    }
+    return (SAFETY * error_norm.powf(-1.0 / 3.0)).clamp(MIN_FACTOR, MAX_FACTOR);
+}
+
+/// Continues integrating a mass action system with adaptive Rosenbrock steps from the last recorded point of a trajectory,
+/// recording the state after every accepted step
+pub fn integrate(system: &MassActionSystem, trajectory: &mut OdeTrajectory, settings: &OdeSettings) -> IntegrationOutcome {
+    let end_time = settings.end_time.unwrap_or(f64::INFINITY);
+
+    let (mut time, mut state) = trajectory.last();
+    let mut derivatives = vec![0.0; state.len()];
+    system.get_derivatives(&state, &mut derivatives);
+
+    let mut h = initial_step(&state, &derivatives);
+
+    while time < end_time {
+        if settings.end_time.is_none() && is_steady(&state, &derivatives, settings) {
+            break;
+        }
+        if trajectory.num_steps() >= settings.max_steps {
+            println!("WARNING: ODE integration stopped after {} steps at time {} without reaching its end", trajectory.num_steps(), time);
+            break;
+        }
+
+        h = h.min(end_time - time);
+        let attempt = step(system, &state, &derivatives, h);
+        let norm = match &attempt {
+            Some(attempt) => error_norm(&attempt.error, &state, &attempt.state, settings),
+            None => f64::INFINITY,
+        };
+
+        if let (Some(attempt), true) = (attempt, norm <= 1.0) {
+            time = if end_time - time <= h { end_time } else { time + h };
+            state = attempt.state;
+            derivatives = attempt.derivatives;
+            trajectory.push(time, state.clone());
+            h *= step_factor(norm);
+        } else {
+            h *= step_factor(norm).min(1.0);
+        }
+
+        if time < end_time && h <= f64::EPSILON * time.abs().max(1.0) {
+            println!("WARNING: ODE step size underflow at time {}", time);
+            break;
+        }
+    }
+
+    return IntegrationOutcome::Finished;
+}
+
+/// LU decomposition with partial pivoting of a dense square matrix
+struct LuDecomposition {
+    lu: Vec<Vec<f64>>,
+    pivots: Vec<usize>,
+}
+
+impl LuDecomposition {
+
+    /// decomposes a matrix in place, returns None if it is singular
+    fn from(mut matrix: Vec<Vec<f64>>) -> Option<Self> {
+        let n = matrix.len();
+        let mut pivots: Vec<usize> = (0..n).collect();
+
+        for column in 0..n {
+            // find the largest remaining entry in this column to use as the pivot
+            let mut pivot = column;
+            for row in column + 1..n {
+                if matrix[row][column].abs() > matrix[pivot][column].abs() {
+                    pivot = row;
+                }
+            }
+            if matrix[pivot][column] == 0.0 {
+                return None;
+            }
+            matrix.swap(column, pivot);
+            pivots.swap(column, pivot);
+
+            let (upper, lower) = matrix.split_at_mut(column + 1);
+            let pivot_row = &upper[column];
+            for row in lower.iter_mut() {
+                let factor = row[column] / pivot_row[column];
+                row[column] = factor;
+                for (entry, pivot_entry) in row.iter_mut().zip(pivot_row.iter()).skip(column + 1) {
+                    *entry -= factor * pivot_entry;
+                }
+            }
+        }
+
+        return Some(Self{lu: matrix, pivots});
+    }
+
+    /// solves `A x = b` for x
+    fn solve(&self, b: Vec<f64>) -> Vec<f64> {
+        let n = b.len();
+        let mut x: Vec<f64> = self.pivots.iter().map(|pivot| b[*pivot]).collect();
+
+        // forward substitution with the unit lower triangle
+        for row in 0..n {
+            for k in 0..row {
+                x[row] -= self.lu[row][k] * x[k];
+            }
+        }
+        // back substitution with the upper triangle
+        for row in (0..n).rev() {
+            for k in row + 1..n {
+                x[row] -= self.lu[row][k] * x[k];
+            }
+            x[row] /= self.lu[row][row];
+        }
+
+        return x;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lu_solve() {
+        let matrix = vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 0.0],
+            vec![3.0, 0.0, 1.0],
+        ];
+        let lu = LuDecomposition::from(matrix).unwrap();
+
+        // x = (1, 2, 3)
+        let x = lu.solve(vec![7.0, 3.0, 6.0]);
+        for (value, expected) in x.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((value - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_singular_matrix() {
+        assert!(LuDecomposition::from(vec![vec![1.0, 2.0], vec![2.0, 4.0]]).is_none());
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::{self, Display};
+
+/// How serious a diagnostic is
+/// - `Error`: the input is invalid and cannot be simulated
+/// - `Warning`: the input is valid but was normalized or is likely to be a mistake
+/// - `Note`: the input is valid but something about it may be worth checking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A problem found while parsing an input file, pointing at the characters it was found at
+/// - `file`: the path of the input file
+/// - `line`, `column`: where the problem starts, both counting from 1, or 0 for a problem with the whole file
+/// - `length`: the number of characters the problem spans, at least 1
+/// - `snippet`: the full text of the line the problem was found on
+/// - `message`: what the problem is
+/// - `hint`: how the problem may be fixed, if there is an obvious fix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    file: String,
+    line: usize,
+    column: usize,
+    length: usize,
+    snippet: String,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Diagnostic {
+
+    pub fn new(severity: Severity, file: &str, line: usize, column: usize, length: usize, snippet: &str, message: String) -> Self {
+        return Self{
+            severity,
+            file: file.to_string(),
+            line,
+            column,
+            length: length.max(1),
+            snippet: snippet.to_string(),
+            message,
+            hint: None,
+        };
+    }
+
+    /// A problem with a whole file rather than any line of it, such as a file which cannot be read
+    pub fn for_file(severity: Severity, file: &str, message: String) -> Self {
+        return Self::new(severity, file, 0, 0, 1, "", message);
+    }
+
+    /// sets how the problem may be fixed
+    pub fn set_hint(&mut self, hint: Option<String>) {
+        self.hint = hint;
+    }
+
+    pub fn get_severity(&self) -> Severity {
+        return self.severity;
+    }
+
+    /// returns true if the diagnostic makes the input invalid
+    pub fn is_error(&self) -> bool {
+        return self.severity == Severity::Error;
+    }
+
+    pub fn get_file(&self) -> &str {
+        return &self.file;
+    }
+
+    pub fn get_line(&self) -> usize {
+        return self.line;
+    }
+
+    pub fn get_column(&self) -> usize {
+        return self.column;
+    }
+
+    pub fn get_snippet(&self) -> &str {
+        return &self.snippet;
+    }
+
+    pub fn get_message(&self) -> &str {
+        return &self.message;
+    }
+
+    pub fn get_hint(&self) -> Option<&str> {
+        return self.hint.as_deref();
+    }
+}
+
+// writes the diagnostic with the offending line and a marker under the characters it points at, e.g.
+// ERROR: invalid reaction rate '-2'
+//   --> input.csv:3:13
+//    |
+//  3 | A + B => C, -2
+//    |             ^^
+//    = hint: rates are non negative numbers such as '3', '0.35', or '1e-4', or the name of a parameter
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "ERROR",
+            Severity::Warning => "WARNING",
+            Severity::Note => "NOTE",
+        };
+        let gutter = " ".repeat(self.line.to_string().len());
+
+        writeln!(f, "{}: {}", severity, self.message)?;
+        if self.line == 0 {
+            // a problem with the whole file has no line to show
+            write!(f, "{}--> {}", gutter, self.file)?;
+        } else {
+            writeln!(f, "{}--> {}:{}:{}", gutter, self.file, self.line, self.column)?;
+            writeln!(f, "{} |", gutter)?;
+            writeln!(f, "{} | {}", self.line, self.snippet)?;
+            write!(f, "{} | {}{}", gutter, " ".repeat(self.column - 1), "^".repeat(self.length))?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\n{} = hint: {}", gutter, hint)?;
+        }
+        return Ok(());
+    }
+}
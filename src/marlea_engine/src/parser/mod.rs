@@ -0,0 +1,677 @@
+/// # Description
+/// The reaction language of input files, read by a tokenizer and a recursive descent grammar.
+/// An input file is a list of reactions, optionally followed or interrupted by a `[parameters]` section
+/// naming rates which reactions may use in place of a number. A `[reactions]` header returns to the reactions.
+/// Every line holds at most one reaction, parameter, or section header, and a `#` starts a comment:
+///
+/// ```text
+/// header    := '[' ('parameters' | 'reactions') ']'
+/// parameter := name '=' number
+/// reaction  := [label ':'] side '=>' side ',' rate [',' delay]
+///            | [label ':'] side ('<=>' | '<->') side ',' rate ',' rate
+/// side      := [term ('+' term)*]
+/// term      := [coefficient] name
+/// rate      := number | parameter name
+/// delay     := number | name '(' number (',' number)* ')'
+/// ```
+///
+/// Parsing does not stop at the first problem. Every problem is recorded as a `Diagnostic` pointing at the line and column
+/// it was found at, and parsing recovers at the next term or line, so every problem in a file is reported in one pass.
+/// Inputs which are valid but were normalized, such as a species written twice on one side, produce warnings.
+/// Init files are read the same way by `parse_init()`, one `species, count[, threshold]` per line.
+
+pub mod tokenizer;
+pub mod diagnostic;
+
+use std::collections::BTreeMap;
+use crate::trial::reaction_network::reaction::{Reaction, delay::Delay, term::Term, label::Label};
+use crate::trial::threshold::Threshold;
+use tokenizer::{Token, TokenKind, tokenize};
+use diagnostic::{Diagnostic, Severity};
+
+const RATE_HINT: &str = "rates are non negative numbers such as '3', '0.35', or '1e-4', or the name of a parameter";
+const DELAY_HINT: &str = "a delay is a duration such as '5', 'fixed(5)', 'exp(5)', 'uniform(1, 3)', or 'gamma(4, 0.5)'";
+const PARAMETER_HINT: &str = "a parameter is written 'name = value' in a '[parameters]' section, e.g. 'k1 = 3.5'";
+const COUNT_HINT: &str = "initial counts are whole non negative numbers such as '0' or '250'";
+
+// the sections of an input file, each started by a header line
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Reactions,
+    Parameters,
+}
+
+/// Parses every reaction in the text of an input file, returning each reaction with the line it was written on
+/// along with every diagnostic found, sorted by line. Both halves of a reversible reaction are returned with the same line.
+/// Reactions are only returned for lines without errors, so the reactions are incomplete if any diagnostic is an error
+pub fn parse_reactions(file: &str, text: &str) -> (Vec<(usize, Reaction)>, Vec<Diagnostic>) {
+    let mut reactions = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    // parameters may be used by reactions written before them, so every section is read before any reaction
+    let no_parameters = BTreeMap::new();
+    let mut parameters: BTreeMap<String, f64> = BTreeMap::new();
+    let mut parameter_lines: BTreeMap<String, usize> = BTreeMap::new();
+    let mut reaction_lines: Vec<(usize, &str)> = Vec::new();
+    let mut section = Section::Reactions;
+    for (index, line) in text.lines().enumerate() {
+        let mut parser = LineParser::new(file, index + 1, line, &no_parameters, &mut diagnostics);
+
+        // lines without anything, including lines of empty comma separated fields, are skipped
+        if parser.tokens.iter().all(|token| *token.get_kind() == TokenKind::Comma) {
+            continue;
+        }
+        if parser.peek_kind() == Some(&TokenKind::LeftBracket) {
+            section = parser.parse_header().unwrap_or(section);
+            continue;
+        }
+        match section {
+            Section::Reactions => reaction_lines.push((index + 1, line)),
+            Section::Parameters => if let Some((name, token, value)) = parser.parse_parameter() {
+                match parameter_lines.get(&name) {
+                    Some(line) => {
+                        let message = format!("parameter {} is already defined on line {}", name, line);
+                        parser.error(token.get_column(), token.get_length(), message, None);
+                    }
+                    None => {
+                        parameter_lines.insert(name.clone(), index + 1);
+                        parameters.insert(name, value);
+                    }
+                }
+            },
+        }
+    }
+
+    let mut label_lines: BTreeMap<String, usize> = BTreeMap::new();
+    for (line, text) in reaction_lines {
+        let mut parser = LineParser::new(file, line, text, &parameters, &mut diagnostics);
+        let label = parser.parse_label();
+        if let Some((name, token)) = &label {
+            match label_lines.get(name) {
+                Some(other_line) => {
+                    let message = format!("reaction label {} is already used on line {}", name, other_line);
+                    parser.error(token.get_column(), token.get_length(), message, Some("every reaction label names a single reaction"));
+                }
+                None => {
+                    label_lines.insert(name.clone(), line);
+                }
+            }
+        }
+
+        for mut reaction in parser.parse_reaction() {
+            if let Some((name, _)) = &label {
+                let direction = reaction.get_label().and_then(|label| label.get_direction());
+                reaction.set_label(Some(match direction {
+                    Some(direction) => Label::reversible(name.clone(), direction),
+                    None => Label::new(name.clone()),
+                }));
+            }
+            reactions.push((line, reaction));
+        }
+    }
+
+    // parameters were read first, so their diagnostics are put back in line order
+    diagnostics.sort_by_key(|diagnostic| diagnostic.get_line());
+    return (reactions, diagnostics);
+}
+
+/// Parses a single term such as `2 water`, returning None if the text is empty
+/// and every diagnostic found if it is not an optional coefficient followed by a species name
+pub fn parse_term(text: &str) -> Result<Option<Term>, Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    let parameters = BTreeMap::new();
+    let mut parser = LineParser::new("<term>", 1, text, &parameters, &mut diagnostics);
+    if parser.tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let term = parser.parse_term();
+    if let Some(token) = parser.peek().cloned() {
+        let found = parser.describe(&token);
+        parser.error(token.get_column(), text.chars().count() + 1 - token.get_column(), format!("expected a single term but found {} after it", found), None);
+    }
+
+    return match term {
+        Some(term) if diagnostics.is_empty() => Ok(Some(term)),
+        _ => Err(diagnostics),
+    };
+}
+
+/// Parses every line of an init file, setting the initial count of every species in `initial_counts` which it sets.
+/// Returns the thresholds given after any species' initial count, in the order they appear, along with every diagnostic found.
+/// Species which are not in `initial_counts` are warned about and ignored, but their thresholds are still returned
+pub fn parse_init(file: &str, text: &str, initial_counts: &mut BTreeMap<String, u64>) -> (Vec<Threshold>, Vec<Diagnostic>) {
+    let mut thresholds = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        // anything after a `#` is a comment
+        let fields = init_fields(line.split('#').next().unwrap_or_default());
+        let mut report = |severity: Severity, column: usize, length: usize, message: String, hint: Option<&str>| {
+            let mut diagnostic = Diagnostic::new(severity, file, index + 1, column, length, line, message);
+            diagnostic.set_hint(hint.map(str::to_string));
+            diagnostics.push(diagnostic);
+        };
+        let Some((name_column, name)) = fields.first().copied() else {
+            continue;
+        };
+        let end_column = fields.last().map_or(1, |(column, field)| column + field.chars().count());
+
+        let Some((count_column, count)) = fields.get(1).copied() else {
+            report(Severity::Error, end_column, 1, format!("expected an initial count after species {}", name), Some(COUNT_HINT));
+            continue;
+        };
+        let Ok(count) = count.parse::<u64>() else {
+            report(Severity::Error, count_column, count.chars().count(), format!("invalid initial count '{}' for species {}", count, name), Some(COUNT_HINT));
+            continue;
+        };
+
+        if fields.len() > 2 {
+            let threshold_fields: Vec<&str> = fields[2..].iter().map(|(_, field)| *field).collect();
+            match Threshold::parse(name, &threshold_fields) {
+                Ok(threshold) => thresholds.extend(threshold),
+                Err(error) => report(Severity::Error, fields[2].0, end_column - fields[2].0, error, None),
+            }
+        }
+
+        match initial_counts.get_mut(name) {
+            Some(initial_count) => *initial_count = count,
+            None => report(Severity::Warning, name_column, name.chars().count(),
+                format!("species {} does not appear in any reaction, its initial count is ignored", name), None),
+        }
+    }
+    return (thresholds, diagnostics);
+}
+
+// splits a line of an init file into its non empty comma separated fields, each with the column it starts at
+fn init_fields(line: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut column = 1;
+    for field in line.split(',') {
+        let leading = field.chars().take_while(|character| character.is_whitespace()).count();
+        if !field.trim().is_empty() {
+            fields.push((column + leading, field.trim()));
+        }
+        column += field.chars().count() + 1;
+    }
+    return fields;
+}
+
+// parses the tokens of a single line, recording every problem found in the shared diagnostics
+struct LineParser<'a> {
+    file: &'a str,
+    line: usize,
+    text: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+    has_error: bool,
+    parameters: &'a BTreeMap<String, f64>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> LineParser<'a> {
+
+    fn new(file: &'a str, line: usize, text: &'a str, parameters: &'a BTreeMap<String, f64>, diagnostics: &'a mut Vec<Diagnostic>) -> Self {
+        return Self{file, line, text, tokens: tokenize(text), position: 0, has_error: false, parameters, diagnostics};
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.position);
+    }
+
+    fn peek_kind(&self) -> Option<&TokenKind> {
+        return self.peek().map(|token| token.get_kind());
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        return token;
+    }
+
+    // returns the column just after the last token, where anything missing from the end of the line would go
+    fn end_column(&self) -> usize {
+        return self.tokens.last().map_or(1, |token| token.get_end());
+    }
+
+    // returns the text of a token as written in the line
+    fn text_of(&self, token: &Token) -> String {
+        return self.text.chars().skip(token.get_column() - 1).take(token.get_length()).collect();
+    }
+
+    // describes a token for a diagnostic, e.g. `'NaCl'`
+    fn describe(&self, token: &Token) -> String {
+        return format!("'{}'", self.text_of(token));
+    }
+
+    fn diagnostic(&mut self, severity: Severity, column: usize, length: usize, message: String, hint: Option<&str>) {
+        let mut diagnostic = Diagnostic::new(severity, self.file, self.line, column, length, self.text, message);
+        diagnostic.set_hint(hint.map(|hint| hint.to_string()));
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn error(&mut self, column: usize, length: usize, message: String, hint: Option<&str>) {
+        self.has_error = true;
+        self.diagnostic(Severity::Error, column, length, message, hint);
+    }
+
+    // reports the next token as unexpected, or the end of the line if there are no tokens left
+    fn error_expected(&mut self, expected: &str, hint: Option<&str>) {
+        match self.peek().cloned() {
+            Some(token) => {
+                let found = self.describe(&token);
+                self.error(token.get_column(), token.get_length(), format!("expected {} but found {}", expected, found), hint);
+            }
+            None => {
+                let column = self.end_column();
+                self.error(column, 1, format!("expected {} but the line ended", expected), hint);
+            }
+        }
+    }
+
+    // skips tokens until one which may follow a term, so that parsing can recover from a malformed term
+    fn skip_to_term_end(&mut self) {
+        while let Some(kind) = self.peek_kind() {
+            if matches!(kind, TokenKind::Plus | TokenKind::Arrow | TokenKind::ReversibleArrow | TokenKind::Comma) {
+                break;
+            }
+            self.position += 1;
+        }
+    }
+
+    // reports any tokens left after a complete line
+    fn expect_line_end(&mut self, after: &str) {
+        if let Some(token) = self.peek().cloned() {
+            let found = self.describe(&token);
+            self.error(token.get_column(), self.end_column() - token.get_column(), format!("unexpected {} after {}", found, after), None);
+        }
+    }
+
+    // header := '[' ('parameters' | 'reactions') ']'
+    // returns the section the header starts, None if it is malformed
+    fn parse_header(&mut self) -> Option<Section> {
+        const HEADER_HINT: &str = "sections are started by a '[parameters]' or '[reactions]' line";
+        self.advance();
+        let section = match self.peek_kind().cloned() {
+            Some(TokenKind::Name(name)) if name.eq_ignore_ascii_case("parameters") => Section::Parameters,
+            Some(TokenKind::Name(name)) if name.eq_ignore_ascii_case("reactions") => Section::Reactions,
+            Some(TokenKind::Name(name)) => {
+                let token = self.advance().expect("a name was peeked");
+                self.error(token.get_column(), token.get_length(), format!("unknown section '{}'", name), Some(HEADER_HINT));
+                return None;
+            }
+            _ => {
+                self.error_expected("a section name after '['", Some(HEADER_HINT));
+                return None;
+            }
+        };
+        self.advance();
+
+        if self.peek_kind() != Some(&TokenKind::RightBracket) {
+            self.error_expected("']' after the section name", Some(HEADER_HINT));
+            return None;
+        }
+        self.advance();
+        self.expect_line_end("the section header");
+        return if self.has_error { None } else { Some(section) };
+    }
+
+    // parameter := name '=' number
+    // returns the name of the parameter, the token naming it, and its value, None if it is malformed
+    fn parse_parameter(&mut self) -> Option<(String, Token, f64)> {
+        let (name, token) = match self.peek_kind().cloned() {
+            Some(TokenKind::Name(name)) => (name, self.advance().expect("a name was peeked")),
+            _ => {
+                self.error_expected("a parameter name", Some(PARAMETER_HINT));
+                return None;
+            }
+        };
+        if self.peek_kind() != Some(&TokenKind::Equals) {
+            self.error_expected("'=' after the parameter name", Some(PARAMETER_HINT));
+            return None;
+        }
+        self.advance();
+
+        let value = match self.advance() {
+            Some(value_token) => match value_token.get_kind() {
+                TokenKind::Number(number) => number.parse::<f64>().ok().filter(|value| value.is_finite() && *value >= 0.0),
+                _ => None,
+            }.or_else(|| {
+                let found = self.describe(&value_token);
+                self.error(value_token.get_column(), value_token.get_length(), format!("invalid value {} for parameter {}", found, name), Some("parameters are rates, so they are non negative numbers such as '3', '0.35', or '1e-4'"));
+                None
+            }),
+            None => {
+                self.error_expected("a value after '='", Some(PARAMETER_HINT));
+                None
+            }
+        };
+        self.expect_line_end("the parameter value");
+        return value.filter(|_| !self.has_error).map(|value| (name, token, value));
+    }
+
+    // label := name ':'
+    // returns the label of the reaction and the token naming it, None if the reaction is not labelled
+    fn parse_label(&mut self) -> Option<(String, Token)> {
+        let name = match (self.peek_kind().cloned(), self.tokens.get(self.position + 1).map(|token| token.get_kind())) {
+            (Some(TokenKind::Name(name)), Some(TokenKind::Colon)) => name,
+            _ => return None,
+        };
+        let token = self.advance().expect("a name was peeked");
+        self.advance();
+        return Some((name, token));
+    }
+
+    // reaction := side arrow side ',' rate [',' delay] | side reversible_arrow side ',' rate ',' rate
+    // returns the reactions of the line, none if it had any errors
+    fn parse_reaction(&mut self) -> Vec<Reaction> {
+        let reactants = self.parse_side("reactants");
+
+        let reversible = match self.peek_kind() {
+            Some(TokenKind::Arrow) => false,
+            Some(TokenKind::ReversibleArrow) => true,
+            _ => {
+                self.error_expected("'=>' or '<=>' between the reactants and products", Some("a reaction is written 'reactants => products, rate', e.g. '2 A + B => C, 1'"));
+                return Vec::new();
+            }
+        };
+        self.advance();
+        let products = self.parse_side("products");
+
+        // every field after the products starts with a comma
+        let rate = self.parse_field("the reaction rate", RATE_HINT).and_then(|token| self.parse_rate(&token));
+
+        if reversible {
+            let reverse_rate = self.parse_field("the reverse reaction rate", "a reversible reaction is written 'reactants <=> products, forward_rate, reverse_rate'")
+                .and_then(|token| self.parse_rate(&token));
+            if let Some(token) = self.peek().cloned() {
+                let length = self.end_column() - token.get_column();
+                self.error(token.get_column(), length, String::from("reversible reactions may not be delayed"), Some("write the forward and reverse reactions on separate lines to delay them"));
+            }
+            if self.has_error {
+                return Vec::new();
+            }
+
+            let (forward, reverse) = Reaction::reversible(Term::merge(reactants), Term::merge(products), rate.unwrap_or(0.0), reverse_rate.unwrap_or(0.0));
+            return vec![forward, reverse];
+        }
+
+        let delay = self.parse_delay();
+        if self.has_error {
+            return Vec::new();
+        }
+
+        let mut reaction = Reaction::new(Term::merge(reactants), Term::merge(products), rate.unwrap_or(0.0));
+        reaction.set_delay(delay);
+        return vec![reaction];
+    }
+
+    // side := [term ('+' term)*]
+    // warns about any species written more than once, which is merged into a single term when the reaction is built
+    fn parse_side(&mut self, side: &str) -> Vec<Term> {
+        let mut terms: Vec<Term> = Vec::new();
+        let start = self.peek().map_or(self.end_column(), |token| token.get_column());
+
+        loop {
+            match self.peek_kind() {
+                None | Some(TokenKind::Arrow | TokenKind::ReversibleArrow | TokenKind::Comma) => break,
+                _ => {}
+            }
+
+            if let Some(term) = self.parse_term() {
+                terms.push(term);
+            }
+
+            match self.peek_kind() {
+                Some(TokenKind::Plus) => {
+                    self.advance();
+                }
+                None | Some(TokenKind::Arrow | TokenKind::ReversibleArrow | TokenKind::Comma) => break,
+                Some(_) => {
+                    self.error_expected("'+' between terms", Some("terms are separated by '+', e.g. 'A + 2 B'"));
+                    self.skip_to_term_end();
+                    if self.peek_kind() == Some(&TokenKind::Plus) {
+                        self.advance();
+                    }
+                }
+            }
+        }
+
+        let end = if self.position > 0 { self.tokens[self.position - 1].get_end() } else { start };
+        let mut occurrences: BTreeMap<String, usize> = BTreeMap::new();
+        for term in &terms {
+            *occurrences.entry(term.get_species_name().to_string()).or_insert(0) += 1;
+        }
+        let merged = match Term::try_merge(terms.iter().cloned()) {
+            Ok(merged) => merged,
+            Err(error) => {
+                self.error(start, end.saturating_sub(start).max(1), error, None);
+                return terms;
+            }
+        };
+        for (species, count) in occurrences {
+            if count > 1 {
+                let coefficient = merged.iter().find(|term| term.get_species_name().to_string() == species).map_or(0, |term| term.get_coefficient());
+                let message = format!("{} appears more than once in the {}, its terms were merged into {} {}", species, side, coefficient, species);
+                self.diagnostic(Severity::Warning, start, end.saturating_sub(start).max(1), message, None);
+            }
+        }
+
+        return terms;
+    }
+
+    // term := [coefficient] name
+    // returns None if the term is malformed, after reporting it and skipping the rest of the term
+    fn parse_term(&mut self) -> Option<Term> {
+        let mut coefficient: Option<u32> = Some(1);
+        let mut coefficient_token: Option<Token> = None;
+
+        if let Some(TokenKind::Number(number)) = self.peek_kind().cloned() {
+            let token = self.advance().expect("a number was peeked");
+            coefficient = self.parse_coefficient(&number, &token);
+            coefficient_token = Some(token);
+        }
+
+        let name = match self.peek_kind().cloned() {
+            Some(TokenKind::Name(name)) => name,
+            _ => {
+                let expected = if coefficient_token.is_some() { "a species name after the coefficient" } else { "a species name" };
+                self.error_expected(expected, Some("a term is an optional whole number coefficient followed by a species name, e.g. '2 A'"));
+                self.skip_to_term_end();
+                return None;
+            }
+        };
+        let name_token = self.advance().expect("a name was peeked");
+
+        // a coefficient written against its name, such as `2water`, is ambiguous so it must be separated
+        if let Some(token) = &coefficient_token {
+            if token.get_end() == name_token.get_column() {
+                let written = format!("{}{}", self.text_of(token), name);
+                let separated = format!("{} {}", self.text_of(token), name);
+                let hint = format!("write '{}' for a coefficient of {}, species names may not start with a digit", separated, self.text_of(token));
+                self.error(token.get_column(), token.get_length() + name_token.get_length(), format!("the coefficient of '{}' is not separated from its species name", written), Some(&hint));
+                return None;
+            }
+        }
+
+        return coefficient.map(|coefficient| Term::new(name, coefficient));
+    }
+
+    // reports a coefficient which is not a whole number which fits in a u32
+    fn parse_coefficient(&mut self, number: &str, token: &Token) -> Option<u32> {
+        if !number.chars().all(|character| character.is_ascii_digit()) {
+            self.error(token.get_column(), token.get_length(), format!("coefficient {} is not a whole number", number), Some("coefficients count molecules so they are non negative whole numbers"));
+            return None;
+        }
+        return match number.parse::<u32>() {
+            Ok(coefficient) => Some(coefficient),
+            Err(_) => {
+                self.error(token.get_column(), token.get_length(), format!("coefficient {} is too large, coefficients may be at most {}", number, u32::MAX), None);
+                None
+            }
+        };
+    }
+
+    // expects a comma followed by the first token of a field, returning that token
+    fn parse_field(&mut self, field: &str, hint: &str) -> Option<Token> {
+        if self.peek_kind() != Some(&TokenKind::Comma) {
+            // without the comma the rest of the line cannot be split into fields
+            self.error_expected(&format!("',' followed by {}", field), Some(hint));
+            self.position = self.tokens.len();
+            return None;
+        }
+        self.advance();
+        if self.peek().is_none() {
+            self.error_expected(field, Some(hint));
+            return None;
+        }
+        return self.advance();
+    }
+
+    // rate := number | parameter name, which must be finite and non negative
+    fn parse_rate(&mut self, token: &Token) -> Option<f64> {
+        let rate = match token.get_kind() {
+            TokenKind::Number(number) => number.parse::<f64>().ok().filter(|rate| rate.is_finite() && *rate >= 0.0),
+            TokenKind::Name(name) => match self.parameters.get(name) {
+                Some(rate) => Some(*rate),
+                None => {
+                    self.error(token.get_column(), token.get_length(), format!("undefined parameter '{}'", name), Some(PARAMETER_HINT));
+                    return None;
+                }
+            },
+            _ => None,
+        };
+        if rate.is_none() {
+            let found = self.describe(token);
+            self.error(token.get_column(), token.get_length(), format!("invalid reaction rate {}", found), Some(RATE_HINT));
+        }
+        return rate;
+    }
+
+    // delay := number | name '(' number (',' number)* ')'
+    // the delay is the rest of the line after a comma, None if there is no comma
+    fn parse_delay(&mut self) -> Option<Delay> {
+        if self.peek_kind() != Some(&TokenKind::Comma) {
+            if self.peek().is_some() {
+                let token = self.peek().cloned().expect("a token was peeked");
+                let found = self.describe(&token);
+                self.error(token.get_column(), self.end_column() - token.get_column(), format!("unexpected {} after the reaction rate", found), Some("an optional delay follows the rate after a comma, e.g. 'A => B, 1, 5'"));
+            }
+            return None;
+        }
+        self.advance();
+
+        let start = match self.peek() {
+            Some(token) => token.get_column(),
+            None => {
+                self.error_expected("a delay after ','", Some(DELAY_HINT));
+                return None;
+            }
+        };
+        let length = self.end_column() - start;
+        let text: String = self.text.chars().skip(start - 1).take(length).collect();
+        self.position = self.tokens.len();
+
+        return match Delay::parse(&text) {
+            Ok(delay) => Some(delay),
+            Err(_) => {
+                self.error(start, length, format!("invalid reaction delay '{}'", text), Some(DELAY_HINT));
+                None
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_parse_reactions() {
+        let text = "2 A + B => C, 1e-4\n\nC <-> D, 1, 0.5\nA + A => , 2, exp(5)\n";
+        let (reactions, diagnostics) = parse_reactions("input.csv", text);
+
+        let lines: Vec<usize> = reactions.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![1, 3, 3, 4]);
+        assert_eq!(reactions[0].1.get_reaction_rate(), 1e-4);
+        assert_eq!(reactions[2].1.get_reaction_rate(), 0.5);
+        assert_eq!(reactions[3].1.get_reactants(), &HashSet::from([Term::new(String::from("A"), 2)]));
+        assert_eq!(reactions[3].1.get_delay(), Some(&Delay::Exponential(5.0)));
+
+        // A + A is merged with a warning
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get_severity(), Severity::Warning);
+        assert_eq!((diagnostics[0].get_line(), diagnostics[0].get_column()), (4, 1));
+    }
+
+    #[test]
+    fn test_merged_coefficient_overflow() {
+        let (reactions, diagnostics) = parse_reactions("input.csv", "B => 4000000000 A + 4000000000 A, 1\n");
+        assert!(reactions.is_empty());
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert_eq!((diagnostics[0].get_line(), diagnostics[0].get_column()), (1, 6));
+        assert_eq!(diagnostics[0].get_message(), "the merged coefficient of A is too large, coefficients may be at most 4294967295");
+    }
+
+    #[test]
+    fn test_labels_and_parameters() {
+        let text = "# binding\nbind: A + B <=> C, kf, 0.5  # reversible\nr1: C => D, k1\n\n[parameters]\nkf = 2\nk1 = 3.5e-2\n";
+        let (reactions, diagnostics) = parse_reactions("input.csv", text);
+        assert!(diagnostics.is_empty());
+
+        let labels: Vec<String> = reactions.iter().map(|(_, reaction)| reaction.get_label().map_or(String::new(), |label| label.to_string())).collect();
+        assert_eq!(labels, vec!["bind, forward", "bind, reverse", "r1"]);
+        assert_eq!(reactions[0].1.get_reaction_rate(), 2.0);
+        assert_eq!(reactions[2].1.get_reaction_rate(), 0.035);
+
+        // parameters must be defined once, and labels name a single reaction
+        let text = "r1: A => B, k2\nr1: B => A, 1\n[parameters]\nk1 = 1\nk1 = 2\n[constants]\n";
+        let (reactions, diagnostics) = parse_reactions("input.csv", text);
+        assert!(reactions.is_empty());
+
+        let found: Vec<(usize, usize)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()))
+            .collect();
+        assert_eq!(found, vec![(1, 13), (2, 1), (5, 1), (6, 2)]);
+    }
+
+    #[test]
+    fn test_report_every_error() {
+        let text = "2water NaCl => B, 1\nA => B\nA => B, fast\nA + 1.5 B => C, 1, uniform(3, 1)\nA <=> B, 1, 2, 5";
+        let (reactions, diagnostics) = parse_reactions("input.csv", text);
+        assert!(reactions.is_empty());
+
+        let found: Vec<(usize, usize)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()))
+            .collect();
+        assert_eq!(found, vec![(1, 1), (1, 8), (2, 7), (3, 9), (4, 5), (4, 20), (5, 14)]);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.is_error()));
+        assert_eq!(diagnostics[0].get_hint(), Some("write '2 water' for a coefficient of 2, species names may not start with a digit"));
+        assert_eq!(diagnostics[4].get_snippet(), "A + 1.5 B => C, 1, uniform(3, 1)");
+    }
+
+    #[test]
+    fn test_parse_init() {
+        let mut initial_counts = BTreeMap::from([(String::from("A"), 0), (String::from("B"), 0)]);
+        let text = "A, 10 # starts full\nB, 5, GE 2\nZ, 3, LT 1\n";
+        let (thresholds, diagnostics) = parse_init("init.csv", text, &mut initial_counts);
+        assert_eq!(initial_counts, BTreeMap::from([(String::from("A"), 10), (String::from("B"), 5)]));
+        assert_eq!(thresholds.iter().map(|threshold| threshold.to_string()).collect::<Vec<String>>(), vec!["B GE 2", "Z LT 1"]);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(!diagnostics[0].is_error());
+
+        // malformed counts and thresholds are errors rather than being ignored
+        let (thresholds, diagnostics) = parse_init("init.csv", "A, lots\nB, 5, at least 2\nC\n", &mut initial_counts);
+        assert!(thresholds.is_empty());
+        let found: Vec<(usize, usize)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()))
+            .collect();
+        assert_eq!(found, vec![(1, 4), (2, 7), (3, 2)]);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.is_error()));
+        assert_eq!(initial_counts[&String::from("A")], 10);
+    }
+}
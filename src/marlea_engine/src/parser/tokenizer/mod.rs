@@ -0,0 +1,196 @@
+/// # Description
+/// Splits a single line of the reaction language into tokens.
+/// Every token records the column it starts at and its length in characters so that diagnostics can point at it.
+/// Tokenizing never fails, characters which cannot start any token become `Unknown` tokens for the grammar to report.
+/// A `#` starts a comment which runs to the end of the line and is skipped.
+
+/// The kinds of token in the reaction language
+/// - `Name`: a species or delay name, starting with a letter or `_` and followed by letters, digits, or any of `_ . ' * -`
+/// - `Number`: a decimal number with an optional sign, fraction, and exponent, e.g. `3`, `-1`, `0.35`, or `1e-4`
+/// - `Plus`: `+`, which separates the terms of one side of a reaction
+/// - `Arrow`: `=>`, which separates the reactants of a reaction from its products
+/// - `ReversibleArrow`: `<=>` or `<->`, which separates the two sides of a reversible reaction
+/// - `Comma`, `LeftParen`, `RightParen`: `,`, `(`, and `)`
+/// - `Colon`: `:`, which separates a reaction's label from the reaction
+/// - `Equals`: `=`, which separates a parameter's name from its value
+/// - `LeftBracket`, `RightBracket`: `[` and `]`, which surround the name of a section
+/// - `Unknown`: any other character
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Name(String),
+    Number(String),
+    Plus,
+    Arrow,
+    ReversibleArrow,
+    Comma,
+    LeftParen,
+    RightParen,
+    Colon,
+    Equals,
+    LeftBracket,
+    RightBracket,
+    Unknown(char),
+}
+
+/// A token and where it was found in its line
+/// - `column`: the column of the first character of the token, counting from 1
+/// - `length`: the number of characters in the token
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    kind: TokenKind,
+    column: usize,
+    length: usize,
+}
+
+impl Token {
+
+    pub fn get_kind(&self) -> &TokenKind {
+        return &self.kind;
+    }
+
+    /// returns the column of the first character of the token, counting from 1
+    pub fn get_column(&self) -> usize {
+        return self.column;
+    }
+
+    /// returns the number of characters in the token
+    pub fn get_length(&self) -> usize {
+        return self.length;
+    }
+
+    /// returns the column just after the last character of the token
+    pub fn get_end(&self) -> usize {
+        return self.column + self.length;
+    }
+}
+
+// returns true if the character may continue a name
+fn is_name_character(character: char) -> bool {
+    return character.is_alphanumeric() || matches!(character, '_' | '.' | '\'' | '*' | '-');
+}
+
+/// splits a line into tokens, skipping whitespace and comments
+pub fn tokenize(line: &str) -> Vec<Token> {
+    let characters: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < characters.len() {
+        let start = index;
+        let character = characters[index];
+        let next = characters.get(index + 1).copied();
+        let starts_with = |text: &str| characters[index..].iter().take(text.len()).copied().eq(text.chars());
+
+        let kind = if character.is_whitespace() {
+            index += 1;
+            continue;
+        } else if character == '#' {
+            break;
+        } else if starts_with("<=>") || starts_with("<->") {
+            index += 3;
+            TokenKind::ReversibleArrow
+        } else if starts_with("=>") {
+            index += 2;
+            TokenKind::Arrow
+        } else if character.is_ascii_digit()
+            || (matches!(character, '-' | '.') && next.is_some_and(|next| next.is_ascii_digit()))
+            || (character == '-' && next == Some('.')) {
+            index = number_end(&characters, index);
+            TokenKind::Number(characters[start..index].iter().collect())
+        } else if character.is_alphabetic() || character == '_' {
+            while index < characters.len() && is_name_character(characters[index]) {
+                index += 1;
+            }
+            TokenKind::Name(characters[start..index].iter().collect())
+        } else {
+            index += 1;
+            match character {
+                '+' => TokenKind::Plus,
+                ',' => TokenKind::Comma,
+                '(' => TokenKind::LeftParen,
+                ')' => TokenKind::RightParen,
+                ':' => TokenKind::Colon,
+                '=' => TokenKind::Equals,
+                '[' => TokenKind::LeftBracket,
+                ']' => TokenKind::RightBracket,
+                other => TokenKind::Unknown(other),
+            }
+        };
+
+        tokens.push(Token{kind, column: start + 1, length: index - start});
+    }
+
+    return tokens;
+}
+
+// returns the index just after the number starting at some index
+fn number_end(characters: &[char], start: usize) -> usize {
+    let digits = |mut index: usize| {
+        while index < characters.len() && characters[index].is_ascii_digit() {
+            index += 1;
+        }
+        return index;
+    };
+
+    let mut index = start;
+    if characters[index] == '-' {
+        index += 1;
+    }
+    index = digits(index);
+    if characters.get(index) == Some(&'.') {
+        index = digits(index + 1);
+    }
+
+    // an exponent is only part of the number if digits follow it, so `2e` is the number 2 followed by the name e
+    if matches!(characters.get(index), Some('e' | 'E')) {
+        let mut exponent = index + 1;
+        if matches!(characters.get(exponent), Some('+' | '-')) {
+            exponent += 1;
+        }
+        if characters.get(exponent).is_some_and(|character| character.is_ascii_digit()) {
+            index = digits(exponent);
+        }
+    }
+    return index;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let kinds: Vec<TokenKind> = tokenize("2 A + B_1 <=> C, 1e-4, 0.5").into_iter().map(|token| token.kind).collect();
+        assert_eq!(kinds, vec![
+            TokenKind::Number(String::from("2")),
+            TokenKind::Name(String::from("A")),
+            TokenKind::Plus,
+            TokenKind::Name(String::from("B_1")),
+            TokenKind::ReversibleArrow,
+            TokenKind::Name(String::from("C")),
+            TokenKind::Comma,
+            TokenKind::Number(String::from("1e-4")),
+            TokenKind::Comma,
+            TokenKind::Number(String::from("0.5")),
+        ]);
+
+        // a coefficient written against its species name is two adjacent tokens
+        let tokens = tokenize("2water => ?");
+        assert_eq!(tokens[0], Token{kind: TokenKind::Number(String::from("2")), column: 1, length: 1});
+        assert_eq!(tokens[1], Token{kind: TokenKind::Name(String::from("water")), column: 2, length: 5});
+        assert_eq!(tokens[2].kind, TokenKind::Arrow);
+        assert_eq!(tokens[3], Token{kind: TokenKind::Unknown('?'), column: 11, length: 1});
+
+        // comments are skipped to the end of the line
+        let kinds: Vec<TokenKind> = tokenize("r1: A => B, k1 # fast").into_iter().map(|token| token.kind).collect();
+        assert_eq!(kinds, vec![
+            TokenKind::Name(String::from("r1")),
+            TokenKind::Colon,
+            TokenKind::Name(String::from("A")),
+            TokenKind::Arrow,
+            TokenKind::Name(String::from("B")),
+            TokenKind::Comma,
+            TokenKind::Name(String::from("k1")),
+        ]);
+    }
+}
@@ -0,0 +1,509 @@
+/// # Description
+/// Streaming statistics over the final solutions of stochastic trials.
+/// Every trial's result is folded into the aggregate as soon as it is received so no solutions need to be kept,
+/// and every trial counts towards the statistics even if its outcome is identical to an earlier trial's.
+///
+/// Per species means and variances are accumulated with Welford's algorithm, which stays numerically stable
+/// for large counts and large numbers of trials where summing squares would lose precision.
+/// Averages hide multimodal outcomes, such as consensus networks ending with all of one species or all of another,
+/// so an exact frequency table of every species' final counts is also kept for quantiles and histograms.
+/// The number of trials stopped by each threshold is tallied so the probability of reaching it can be reported, as in Aleae.
+/// When precision targets are set the aggregate also reports the confidence interval achieved for each targeted species.
+/// Every statistic is generic over the count type trials ran with, so arbitrary precision counts are summarised exactly.
+
+use std::collections::{BTreeMap, HashMap};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use crate::trial::reaction_network::count::Count;
+use crate::trial::threshold::Threshold;
+use precision::{PrecisionResult, PrecisionSettings, critical_value};
+
+pub mod precision;
+
+/// Running statistics of the final count of a single species
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeciesStatistics<C: Count = u64> {
+    samples: u64,
+    mean: f64,
+    // sum of squared differences from the current mean
+    squared_deviations: f64,
+    // the smallest and largest samples, None until there are samples
+    range: Option<(C, C)>,
+}
+
+impl<C: Count> SpeciesStatistics<C> {
+
+    pub fn new() -> Self {
+        return Self{samples: 0, mean: 0.0, squared_deviations: 0.0, range: None};
+    }
+
+    /// folds a single sample into the running statistics
+    pub fn push(&mut self, value: C) {
+        self.samples += 1;
+        let value_f64 = value.to_f64();
+        let delta = value_f64 - self.mean;
+        self.mean += delta / self.samples as f64;
+        self.squared_deviations += delta * (value_f64 - self.mean);
+        self.range = match self.range.take() {
+            Some((min, max)) => Some((min.min(value.clone()), max.max(value))),
+            None => Some((value.clone(), value)),
+        };
+    }
+
+    pub fn get_samples(&self) -> u64 {
+        return self.samples;
+    }
+
+    pub fn get_mean(&self) -> f64 {
+        return self.mean;
+    }
+
+    /// returns the unbiased sample variance, 0 until there are at least two samples
+    pub fn get_variance(&self) -> f64 {
+        if self.samples < 2 {
+            return 0.0;
+        }
+        return self.squared_deviations / (self.samples - 1) as f64;
+    }
+
+    pub fn get_standard_deviation(&self) -> f64 {
+        return self.get_variance().sqrt();
+    }
+
+    /// returns the standard error of the mean
+    pub fn get_standard_error(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        return (self.get_variance() / self.samples as f64).sqrt();
+    }
+
+    /// returns the half-width of the normal approximation confidence interval of the mean at some confidence level
+    pub fn get_confidence_half_width(&self, confidence: f64) -> f64 {
+        return critical_value(confidence) * self.get_standard_error();
+    }
+
+    /// returns the smallest sample, 0 if there are no samples
+    pub fn get_min(&self) -> C {
+        return self.range.as_ref().map_or_else(|| C::from_u64(0), |(min, _)| min.clone());
+    }
+
+    /// returns the largest sample, 0 if there are no samples
+    pub fn get_max(&self) -> C {
+        return self.range.as_ref().map_or_else(|| C::from_u64(0), |(_, max)| max.clone());
+    }
+}
+
+impl<C: Count> Default for SpeciesStatistics<C> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// Exact frequency table of the final count of a single species
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeciesDistribution<C: Count = u64> {
+    frequencies: BTreeMap<C, usize>,
+    samples: usize,
+}
+
+/// A single histogram bin counting the samples from `lower` to `upper` inclusive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramBin<C: Count = u64> {
+    pub lower: C,
+    pub upper: C,
+    pub count: usize,
+}
+
+impl<C: Count> Default for SpeciesDistribution<C> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl<C: Count> SpeciesDistribution<C> {
+
+    pub fn new() -> Self {
+        return Self{frequencies: BTreeMap::new(), samples: 0};
+    }
+
+    /// folds a single sample into the frequency table
+    pub fn push(&mut self, value: C) {
+        *self.frequencies.entry(value).or_insert(0) += 1;
+        self.samples += 1;
+    }
+
+    /// returns how many samples took each value, in increasing order of value
+    pub fn get_frequencies(&self) -> &BTreeMap<C, usize> {
+        return &self.frequencies;
+    }
+
+    pub fn get_samples(&self) -> usize {
+        return self.samples;
+    }
+
+    /// returns the smallest value which at least a fraction `level` of samples are less than or equal to, 0 if there are no samples
+    pub fn get_quantile(&self, level: f64) -> C {
+        let rank = ((level.clamp(0.0, 1.0) * self.samples as f64).ceil() as usize).max(1);
+        let mut cumulative = 0;
+        for (value, frequency) in &self.frequencies {
+            cumulative += frequency;
+            if cumulative >= rank {
+                return value.clone();
+            }
+        }
+        return C::from_u64(0);
+    }
+
+    /// returns a histogram of at most `bins` equal width bins spanning the smallest to the largest sample.
+    /// Bins are never narrower than a single count so fewer bins are returned when the samples span fewer values.
+    pub fn get_histogram(&self, bins: usize) -> Vec<HistogramBin<C>> {
+        // bin edges are computed with arbitrary precision so they are exact for every count type
+        let (min, max) = match (self.frequencies.keys().next(), self.frequencies.keys().next_back()) {
+            (Some(min), Some(max)) => (min.to_biguint(), max.to_biguint()),
+            _ => return Vec::new(),
+        };
+
+        let span = &max - &min + 1_u32;
+        let bins = BigUint::from(bins.max(1));
+        let width = (&span + &bins - 1_u32) / &bins;
+        let num_bins = ((&span + &width - 1_u32) / &width).to_usize().expect("there are at most `bins` bins");
+        let in_range = |value: &BigUint| C::from_biguint(value).expect("bin edges lie between samples");
+        let mut histogram: Vec<HistogramBin<C>> = (0..num_bins)
+            .map(|bin| {
+                let lower = &min + BigUint::from(bin) * &width;
+                let upper = (&lower + &width - 1_u32).min(max.clone());
+                HistogramBin{lower: in_range(&lower), upper: in_range(&upper), count: 0}
+            })
+            .collect();
+
+        for (value, frequency) in &self.frequencies {
+            let bin = ((value.to_biguint() - &min) / &width).to_usize().expect("every sample lies in a bin");
+            histogram[bin].count += frequency;
+        }
+
+        return histogram;
+    }
+}
+
+/// Settings for how a simulation report summarises the distribution of each species
+/// - `quantiles`: the quantile levels reported for every species, between 0 and 1
+/// - `bins`: the maximum number of bins in each species' histogram
+/// - `histogram_path`: a file to write every species' histogram and quantiles to, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportSettings {
+    pub quantiles: Vec<f64>,
+    pub bins: usize,
+    pub histogram_path: Option<String>,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self {
+            quantiles: vec![0.05, 0.25, 0.5, 0.75, 0.95],
+            bins: 10,
+            histogram_path: None,
+        }
+    }
+}
+
+/// Streaming aggregate of the final solutions of many trials over a fixed, alphabetically ordered, list of species
+pub struct EnsembleStatistics<C: Count = u64> {
+    species: Vec<String>,
+    statistics: Vec<SpeciesStatistics<C>>,
+    distributions: Vec<SpeciesDistribution<C>>,
+    outcomes: HashMap<Vec<C>, usize>,
+    thresholds: Vec<(String, usize)>,
+    precision_settings: Option<PrecisionSettings>,
+    num_trials: usize,
+    complete: bool,
+}
+
+impl<C: Count> EnsembleStatistics<C> {
+
+    /// creates an empty aggregate over the species of a compiled network and every threshold which may stop a trial
+    pub fn from(species: &[String], thresholds: &[Threshold]) -> Self {
+        let species = species.to_vec();
+        let statistics = vec![SpeciesStatistics::new(); species.len()];
+        let distributions = vec![SpeciesDistribution::new(); species.len()];
+
+        let thresholds = thresholds.iter().map(|threshold| (threshold.to_string(), 0)).collect();
+
+        return Self{species, statistics, distributions, outcomes: HashMap::new(), thresholds, precision_settings: None, num_trials: 0, complete: true};
+    }
+
+    /// Sets the precision targets the aggregate reports on
+    pub fn set_precision_settings(&mut self, precision_settings: Option<PrecisionSettings>) {
+        self.precision_settings = precision_settings;
+    }
+
+    /// folds the final counts of a single trial, in the order of the species of the aggregate,
+    /// and the index of every threshold it reached, into the aggregate
+    pub fn push(&mut self, counts: &[C], reached_thresholds: &[usize]) {
+        let outcome = counts.to_vec();
+
+        for ((statistics, distribution), count) in self.statistics.iter_mut().zip(self.distributions.iter_mut()).zip(&outcome) {
+            statistics.push(count.clone());
+            distribution.push(count.clone());
+        }
+        *self.outcomes.entry(outcome).or_insert(0) += 1;
+        for index in reached_thresholds {
+            self.thresholds[*index].1 += 1;
+        }
+        self.num_trials += 1;
+    }
+
+    pub fn get_num_trials(&self) -> usize {
+        return self.num_trials;
+    }
+
+    /// Flags the aggregate as incomplete because its run stopped before every trial finished
+    pub fn mark_incomplete(&mut self) {
+        self.complete = false;
+    }
+
+    /// returns the confidence interval half-width achieved for every precision target
+    pub fn get_precision(&self) -> Vec<PrecisionResult> {
+        let settings = match &self.precision_settings {
+            Some(settings) => settings,
+            None => return Vec::new(),
+        };
+
+        return settings.targets.iter()
+            .map(|target| {
+                // species which never appear have a count of 0 in every trial
+                let half_width = self.species.iter()
+                    .position(|name| *name == target.species)
+                    .map(|index| self.statistics[index].get_confidence_half_width(settings.confidence))
+                    .unwrap_or(0.0);
+                PrecisionResult{species: target.species.clone(), target: target.half_width, half_width}
+            })
+            .collect();
+    }
+
+    /// returns true once enough trials have run for every precision target to be met
+    pub fn is_precise(&self) -> bool {
+        return match &self.precision_settings {
+            Some(settings) => self.num_trials >= settings.min_trials.max(2) && self.get_precision().iter().all(|result| result.is_met()),
+            None => false,
+        };
+    }
+
+    /// summarises the aggregate into a report
+    pub fn report(&self) -> SimulationReport<C> {
+        let mut outcomes: Vec<(Vec<C>, usize)> = self.outcomes.iter()
+            .map(|(outcome, count)| (outcome.clone(), *count))
+            .collect();
+        outcomes.sort_by(|first, second| second.1.cmp(&first.1).then_with(|| first.0.cmp(&second.0)));
+
+        return SimulationReport{
+            num_trials: self.num_trials,
+            species: self.species.iter().cloned().zip(self.statistics.iter().cloned()).collect(),
+            distributions: self.distributions.clone(),
+            outcomes,
+            thresholds: self.thresholds.clone(),
+            confidence: self.precision_settings.as_ref().map(|settings| settings.confidence),
+            precision: self.get_precision(),
+            complete: self.complete,
+        };
+    }
+}
+
+/// The statistics of a completed simulation
+/// - `num_trials`: the number of trials which contributed to the statistics
+/// - `species`: the statistics of the final count of every species, sorted alphabetically
+/// - `distributions`: the frequency table of the final count of every species, in the same order as `species`
+/// - `outcomes`: every distinct final state, as counts in the same order as `species`, and how many trials ended in it.
+///               The most common outcomes come first.
+/// - `thresholds`: every threshold and the number of trials which reached it
+/// - `confidence`: the confidence level of the precision targets, None if trials were not run adaptively
+/// - `precision`: the confidence interval half-width achieved for every precision target
+/// - `complete`: false if the run was stopped, e.g. by reaching its maximum runtime, before every trial finished.
+///               `num_trials` is then the number of trials which did finish.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationReport<C: Count = u64> {
+    num_trials: usize,
+    species: Vec<(String, SpeciesStatistics<C>)>,
+    distributions: Vec<SpeciesDistribution<C>>,
+    outcomes: Vec<(Vec<C>, usize)>,
+    thresholds: Vec<(String, usize)>,
+    confidence: Option<f64>,
+    precision: Vec<PrecisionResult>,
+    complete: bool,
+}
+
+impl<C: Count> SimulationReport<C> {
+
+    pub fn get_num_trials(&self) -> usize {
+        return self.num_trials;
+    }
+
+    /// returns false if the run stopped before every trial finished
+    pub fn is_complete(&self) -> bool {
+        return self.complete;
+    }
+
+    /// returns the statistics of every species, sorted alphabetically
+    pub fn get_species(&self) -> &[(String, SpeciesStatistics<C>)] {
+        return &self.species;
+    }
+
+    /// returns the statistics of a single species
+    pub fn get_statistics(&self, species: &str) -> Option<&SpeciesStatistics<C>> {
+        return self.species.iter()
+            .find(|(name, _)| name == species)
+            .map(|(_, statistics)| statistics);
+    }
+
+    /// returns the frequency table of every species, in the same order as `get_species()`
+    pub fn get_distributions(&self) -> &[SpeciesDistribution<C>] {
+        return &self.distributions;
+    }
+
+    /// returns the frequency table of a single species
+    pub fn get_distribution(&self, species: &str) -> Option<&SpeciesDistribution<C>> {
+        return self.species.iter()
+            .position(|(name, _)| name == species)
+            .map(|index| &self.distributions[index]);
+    }
+
+    /// returns every distinct final state and the number of trials which ended in it, most common first
+    pub fn get_outcomes(&self) -> &[(Vec<C>, usize)] {
+        return &self.outcomes;
+    }
+
+    /// returns every threshold and the number of trials which reached it, in the order thresholds were given
+    pub fn get_thresholds(&self) -> &[(String, usize)] {
+        return &self.thresholds;
+    }
+
+    /// returns the fraction of trials which reached the threshold at some index
+    pub fn get_threshold_probability(&self, index: usize) -> f64 {
+        if self.num_trials == 0 {
+            return 0.0;
+        }
+        return self.thresholds[index].1 as f64 / self.num_trials as f64;
+    }
+
+    /// returns the confidence level of the precision targets, None if trials were not run adaptively
+    pub fn get_confidence(&self) -> Option<f64> {
+        return self.confidence;
+    }
+
+    /// returns the confidence interval half-width achieved for every precision target, in the order targets were given
+    pub fn get_precision(&self) -> &[PrecisionResult] {
+        return &self.precision;
+    }
+
+    /// returns true if every precision target was met
+    pub fn precision_targets_met(&self) -> bool {
+        return self.precision.iter().all(|result| result.is_met());
+    }
+
+    /// returns the mean final count of every species, sorted alphabetically
+    pub fn get_means(&self) -> Vec<(String, f64)> {
+        return self.species.iter()
+            .map(|(name, statistics)| (name.clone(), statistics.get_mean()))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trial::threshold::Comparison;
+
+    #[test]
+    fn test_species_statistics() {
+        let mut statistics: SpeciesStatistics = SpeciesStatistics::new();
+        for value in [2, 4, 4, 4, 5, 5, 7, 9] {
+            statistics.push(value);
+        }
+
+        assert_eq!(statistics.get_mean(), 5.0);
+        assert!((statistics.get_variance() - 32.0 / 7.0).abs() < 1e-12);
+        assert!((statistics.get_standard_error() - (32.0 / 7.0 / 8.0_f64).sqrt()).abs() < 1e-12);
+        assert_eq!(statistics.get_min(), 2);
+        assert_eq!(statistics.get_max(), 9);
+    }
+
+    #[test]
+    fn test_quantiles_and_histogram() {
+        // a bimodal outcome, 6 trials ending with 0 and 4 trials ending with 100
+        let mut distribution: SpeciesDistribution = SpeciesDistribution::new();
+        for value in [0, 0, 0, 0, 0, 0, 100, 100, 100, 100] {
+            distribution.push(value);
+        }
+
+        assert_eq!(distribution.get_quantile(0.5), 0);
+        assert_eq!(distribution.get_quantile(0.6), 0);
+        assert_eq!(distribution.get_quantile(0.61), 100);
+        assert_eq!(distribution.get_quantile(1.0), 100);
+
+        let histogram = distribution.get_histogram(4);
+        assert_eq!(histogram.len(), 4);
+        assert_eq!(histogram[0], HistogramBin{lower: 0, upper: 25, count: 6});
+        assert_eq!(histogram[3], HistogramBin{lower: 78, upper: 100, count: 4});
+        assert_eq!(histogram.iter().map(|bin| bin.count).sum::<usize>(), 10);
+
+        // bins are never narrower than a single count
+        let mut narrow: SpeciesDistribution = SpeciesDistribution::new();
+        narrow.push(3);
+        narrow.push(4);
+        assert_eq!(narrow.get_histogram(10).len(), 2);
+    }
+
+    #[test]
+    fn test_identical_outcomes_are_counted() {
+        // two identical trials and one different trial must weigh the mean 2:1
+        let mut ensemble: EnsembleStatistics = EnsembleStatistics::from(&[String::from("A"), String::from("B")], &[]);
+        ensemble.push(&[3, 0], &[]);
+        ensemble.push(&[3, 0], &[]);
+        ensemble.push(&[0, 6], &[]);
+
+        let report = ensemble.report();
+        assert_eq!(report.get_num_trials(), 3);
+        assert_eq!(report.get_means(), vec![(String::from("A"), 2.0), (String::from("B"), 2.0)]);
+        assert_eq!(report.get_outcomes(), &[(vec![3, 0], 2), (vec![0, 6], 1)]);
+    }
+
+    #[test]
+    fn test_threshold_probabilities() {
+        let thresholds = [
+            Threshold::new(String::from("A"), Comparison::GE, 3),
+            Threshold::new(String::from("B"), Comparison::GE, 6),
+        ];
+        let mut ensemble: EnsembleStatistics = EnsembleStatistics::from(&[String::from("A"), String::from("B")], &thresholds);
+        ensemble.push(&[3, 0], &[0]);
+        ensemble.push(&[0, 6], &[1]);
+        ensemble.push(&[3, 0], &[0]);
+        ensemble.push(&[1, 1], &[]);
+
+        let report = ensemble.report();
+        assert_eq!(report.get_thresholds(), &[(String::from("A GE 3"), 2), (String::from("B GE 6"), 1)]);
+        assert_eq!(report.get_threshold_probability(0), 0.5);
+        assert_eq!(report.get_threshold_probability(1), 0.25);
+    }
+
+    #[test]
+    fn test_precision_targets() {
+        let mut ensemble: EnsembleStatistics = EnsembleStatistics::from(&[String::from("A")], &[]);
+        ensemble.set_precision_settings(Some(PrecisionSettings{
+            targets: vec![precision::PrecisionTarget{species: String::from("A"), half_width: 0.5}],
+            min_trials: 4,
+            ..PrecisionSettings::default()
+        }));
+
+        // alternating 0 and 2 has a standard deviation of about 1 so the half-width shrinks like 1.96 / sqrt(n)
+        for trial in 0..17 {
+            assert!(!ensemble.is_precise());
+            ensemble.push(&[(trial % 2) * 2], &[]);
+        }
+        assert!(ensemble.is_precise());
+
+        let report = ensemble.report();
+        assert_eq!(report.get_confidence(), Some(0.95));
+        assert!(report.precision_targets_met());
+        assert!(report.get_precision()[0].half_width < 0.5);
+    }
+}
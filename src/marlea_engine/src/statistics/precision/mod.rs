@@ -0,0 +1,140 @@
+/// # Description
+/// Confidence interval targets for running trials adaptively.
+/// Rather than a fixed number of trials, a target half-width of the confidence interval of a species' mean final count may be given,
+/// e.g. `Y=0.5` to estimate the mean of `Y` to within ±0.5. Trials are run until every target is met or a cap is reached.
+///
+/// The half-width of a confidence interval is `z * standard_error`, where `z` is the quantile of the standard normal distribution
+/// for the requested confidence level. The normal approximation is accurate once a few dozen trials have run,
+/// so targets are never considered met before `min_trials` trials.
+
+use std::str::FromStr;
+
+/// A target half-width for the confidence interval of the mean final count of a single species
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecisionTarget {
+    pub species: String,
+    pub half_width: f64,
+}
+
+impl FromStr for PrecisionTarget {
+    type Err = String;
+
+    /// parses a target written `<species>=<half width>`, e.g. `Y=0.5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid precision target '{}', expected a species and a confidence interval half-width such as 'Y=0.5'", s);
+        let (species, half_width) = s.split_once('=').ok_or_else(invalid)?;
+        let species = species.trim();
+        let half_width = half_width.trim().parse::<f64>().map_err(|_| invalid())?;
+
+        if species.is_empty() || !half_width.is_finite() || half_width <= 0.0 {
+            return Err(invalid());
+        }
+        return Ok(Self{species: species.to_string(), half_width});
+    }
+}
+
+/// Settings for running trials until the mean of every targeted species is known precisely enough
+/// - `targets`: the confidence interval half-width required for each species
+/// - `confidence`: the confidence level of every interval, between 0 and 1
+/// - `min_trials`: the number of trials run before targets are checked
+/// - `max_trials`: the number of trials after which the simulation stops even if targets are not met
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecisionSettings {
+    pub targets: Vec<PrecisionTarget>,
+    pub confidence: f64,
+    pub min_trials: usize,
+    pub max_trials: usize,
+}
+
+impl Default for PrecisionSettings {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            confidence: 0.95,
+            min_trials: 30,
+            max_trials: 10_000,
+        }
+    }
+}
+
+/// The precision achieved for a single target
+/// - `species`: the targeted species
+/// - `target`: the required confidence interval half-width
+/// - `half_width`: the confidence interval half-width achieved
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecisionResult {
+    pub species: String,
+    pub target: f64,
+    pub half_width: f64,
+}
+
+impl PrecisionResult {
+
+    pub fn is_met(&self) -> bool {
+        return self.half_width <= self.target;
+    }
+}
+
+/// Returns the number of standard errors either side of a mean which contain the given fraction of a normal distribution,
+/// e.g. about 1.96 for a confidence level of 0.95
+pub fn critical_value(confidence: f64) -> f64 {
+    return normal_quantile(0.5 + confidence / 2.0);
+}
+
+/// Computes the inverse of the standard normal cumulative distribution function,
+/// using Acklam's rational approximation which has a relative error below 1.2e-9
+// the coefficients are kept exactly as published
+#[allow(clippy::excessive_precision)]
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02, 1.383577518672690e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+    const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02, 6.680131188771972e+01, -1.328068155288572e+01];
+    const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00, -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+    const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00, 3.754408661907416e+00];
+    const P_LOW: f64 = 0.02425;
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    // rational approximation for the lower tail, the upper tail follows by symmetry
+    let tail = |p: f64| {
+        let q = (-2.0 * p.ln()).sqrt();
+        return (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0);
+    };
+
+    if p < P_LOW {
+        return tail(p);
+    }
+    if p > 1.0 - P_LOW {
+        return -tail(1.0 - p);
+    }
+
+    let q = p - 0.5;
+    let r = q * q;
+    return (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+        / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_precision_target() {
+        assert_eq!("Y=0.5".parse::<PrecisionTarget>(), Ok(PrecisionTarget{species: String::from("Y"), half_width: 0.5}));
+        assert_eq!(" X = 2 ".parse::<PrecisionTarget>(), Ok(PrecisionTarget{species: String::from("X"), half_width: 2.0}));
+        assert!("Y".parse::<PrecisionTarget>().is_err());
+        assert!("Y=-1".parse::<PrecisionTarget>().is_err());
+    }
+
+    #[test]
+    fn test_critical_value() {
+        assert!((critical_value(0.95) - 1.959964).abs() < 1e-6);
+        assert!((critical_value(0.99) - 2.575829).abs() < 1e-6);
+        assert!((normal_quantile(0.01) + 2.326348).abs() < 1e-6);
+    }
+}
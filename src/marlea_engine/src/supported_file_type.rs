@@ -1,9 +1,13 @@
-use crate::trial::reaction_network::reaction::{Reaction, term::{Term, solution::Species}};
-use csv::ReaderBuilder;
-use std::sync::mpsc::Receiver;
+use crate::trial::reaction_network::reaction::{Reaction, term::solution::Species};
+use crate::parser::{self, diagnostic::{Diagnostic, Severity}};
+use crate::trial::reaction_network::duplicates::{DuplicatePolicy, resolve_duplicates};
 use std::path::Path;
-use std::collections::{HashMap, HashSet};
-use super::trial::reaction_network::reaction::term::solution::Solution;
+use std::collections::{BTreeMap, HashMap};
+use crate::ode::OdeTrajectory;
+use crate::fsp::ProbabilityDistribution;
+use crate::statistics::{ReportSettings, SimulationReport, SpeciesDistribution};
+use crate::trial::threshold::Threshold;
+use crate::trial::reaction_network::count::Count;
 
 pub enum SupportedFileType {
 CSV(String),
@@ -23,167 +27,342 @@ impl SupportedFileType {
             Some("csv") => Self::CSV(file_path),
             Some("json") => Self::JSON(file_path),
             Some("xml") => Self::XML(file_path),
-            Some(other_file_type) => Self::Unsuported(other_file_type.to_string()),
-            _=> panic!("no_file_extension_found")
+            _ => Self::Unsuported(file_path),
         }
     }
 
+    /// Reads the whole text of an input or init file,
+    /// returns an error diagnostic for the whole file if it cannot be read or is not a CSV file
+    pub fn read_text(&self) -> Result<String, Vec<Diagnostic>> {
+        return match self {
+            Self::CSV(path) => std::fs::read_to_string(path)
+                .map_err(|error| vec![Diagnostic::for_file(Severity::Error, path, format!("could not read {}: {}", path, error))]),
+            _ => Err(vec![self.unsupported_input()]),
+        };
+    }
+
+    // describes a file type which reactions or initial counts cannot be read from
+    fn unsupported_input(&self) -> Diagnostic {
+        let (Self::CSV(path) | Self::JSON(path) | Self::XML(path) | Self::Unsuported(path)) = self;
+        return Diagnostic::for_file(Severity::Error, path, format!("unsupported input format for {}, expected a CSV file", path));
+    }
+
     // A function that parses a file into a `ReactionNetworkParts` enum Type
-    pub fn parse_reactions(&self) -> HashSet<Reaction> {
+    // every problem in the file is returned as a diagnostic, warnings are returned with the reactions if the file has no errors.
+    // reactions which are written more than once are reported with their line numbers and handled by the duplicate policy
+    pub fn parse_reactions(&self, duplicates: DuplicatePolicy) -> Result<(Vec<Reaction>, Vec<Diagnostic>), Vec<Diagnostic>> {
         // Handle different types of supported files, starts here with CSV
         match self {
             Self::CSV(path) => {
-                // Read and create CSVReader object
-                let reader = ReaderBuilder::new()
-                    .has_headers(false)
-                    .delimiter(b',')  
-                    .from_path(path); 
-                
-                match reader {
-                    Ok(mut reader) => { // If file exists and was opened successfully
-                        
-                        // Filter empty rows out of the returned reader and panic if reader encounters an error
-                        let records = reader.records().filter_map(
-                            |record|
-                            match record {
-                                Ok(mut string) => {
-                                    string.trim();
-                                    if string[0].is_empty() && string[1].is_empty() {
-                                        None
-                                    } else {
-                                        Some(string)
-                                    }
-                                }
-                                Err(error) => {panic!("{}", error)}
-                            }
-                        );
-                        let mut reactions = HashSet::new();
-                        
-                        for record in records {
-                            let mut reactants: HashSet<Term> = HashSet::new();
-                            let mut products: HashSet<Term> = HashSet::new();
-                            
-                            // Find "=>" and split sides
-                            let sides: Vec<&str> = record[0].split("=>").collect();
-                            if sides.len() != 2 {
-                                panic!("Invalid reaction format - expected 'reactants => products' but received [{}]", record[0].to_string());
-                            }
-                            let left_side: Vec<&str> = sides[0].split('+').collect();
-                            let right_side: Vec<&str> = sides[1].split('+').collect();
-
-
-                            
-                            // Split left side fields into space sign delimited sub fields and parse as reactants
-                            for term_string in left_side {
-                                if let Some(term) = Term::from(term_string) {reactants.insert(term);}
-
-                            }             
-
-                            // Split right side fields into space delimited subfields and parse as products
-                            for term_string in right_side {
-                                if let Some(term) = Term::from(term_string) {products.insert(term);}
-                            }
-
-                            // Parse the last field as reaction_rate
-                            let rate_str = record[1].trim();
-                            let rate = rate_str.parse::<u64>().unwrap_or_else(|_| panic!("Invalid reaction rate '{}' provided", record[1].to_string()));
-
-                            reactions.insert(Reaction::new(reactants, products, rate));
-                        }
+                let text = self.read_text()?;
+                let (reactions, mut diagnostics) = parser::parse_reactions(path, &text);
 
-                        return reactions; 
-                        
-                    }
-                    Err(error) => panic!("{}", error),
-                } // end of inner match { OK(CSVReader) vs. Error(String) }  
+                if diagnostics.iter().any(|diagnostic| diagnostic.is_error()) {
+                    return Err(diagnostics);
+                }
+                let (reactions, warnings) = resolve_duplicates(path, &text, reactions, duplicates)?;
+                diagnostics.extend(warnings);
+                return Ok((reactions, diagnostics));
             }, // End of handling CSV files
-            Self::JSON(_path) => todo!(),
-            Self::XML(_path) => todo!(), 
-            Self::Unsuported(file_type) => panic!("Unsupported file type: found {}, expects CSV", file_type), 
-            
-
+            _ => return Err(vec![self.unsupported_input()]),
         } //  End of outer match {Self} (SupportedFileType enum Type)
     }
     
     /// Parses initial solution from a reaction network based on the file type (CSV, JSON, XML) 
     /// Self: is a parsed set of reactions which will be added to solution with count of 0 if not specieifed in init data
-    pub fn parse_initial_solution(&self, initial_solution: &mut HashMap<Species, Species>) {
-
+    /// Returns the thresholds given after any species' initial count, in the order they appear, along with any warnings,
+    /// or every diagnostic found if any initial count or threshold is malformed
+    pub fn parse_initial_solution(&self, initial_solution: &mut HashMap<Species, Species>) -> Result<(Vec<Threshold>, Vec<Diagnostic>), Vec<Diagnostic>> {
         // Match and handle different file types
         match self {
             Self::CSV(path) => { // CSV file handling
-                let reader = ReaderBuilder::new()
-                    .has_headers(false)
-                    .delimiter(b',')
-                    .from_path(path);
-
-                match reader { 
-                    Ok(mut reader) => {
-                        // Get records and filter out any errors
-                        let records = reader.records().filter_map(Result::ok);
-
-                        for record in records {
-                            // Split each record string based on commas and filter empty fields
-                            let fields: Vec<&str> = record.iter()
-                                .filter_map(|field| {
-                                    let trimmed_field = field.trim();
-                                    if trimmed_field.is_empty() { None } else { Some(trimmed_field) }
-                                })
-                                .collect();
-                        
-                            // If there is less than 2 fields, disregard this record
-                            if fields.len() < 2 {
-                                continue;
-                            }
-                        
-                            // Ignoring spaces parse first non empty field as Species::name
-                            let mut species_name = Species::Name(String::new());
-                            if let Some(name_str) = fields.get(0).map(|s| s.trim()).filter(|s| !s.is_empty()) {
-                                species_name = Species::Name(name_str.to_owned());
-                            }
-                        
-                            // Ignoring spaces parse second non empty field as Species::count
-                            let mut species_count = Species::Count(0);
-                            if let Some(count_str) = fields.get(1).map(|s| s.trim()).filter(|s| !s.is_empty()) {
-                                if let Ok(count_int) = count_str.parse::<u64>() {
-                                    species_count = Species::Count(count_int);
-                                }
-                            }
-                        
-                            // If any is present, parse third non empty field as species threshold
-                            if let Some(_threshold_str) = fields.get(2).map(|s| s.trim()).filter(|s| !s.is_empty()) {
-                                unimplemented!()
-                            }
-
-                            // Add species name and data t
-                            initial_solution.entry(species_name)
-                                .and_modify(|count| *count = species_count);
-                        }
-                    },
-                    Err(error) => panic!("error occurred while reading csv file: {}", error), // Handle reader error here
+                let mut initial_counts: BTreeMap<String, u64> = initial_solution.iter()
+                    .filter_map(|(species, count)| match (species, count) {
+                        (Species::Name(name), Species::Count(count)) => Some((name.clone(), *count)),
+                        _ => None,
+                    })
+                    .collect();
+                let text = self.read_text()?;
+                let (thresholds, diagnostics) = parser::parse_init(path, &text, &mut initial_counts);
+
+                if diagnostics.iter().any(|diagnostic| diagnostic.is_error()) {
+                    return Err(diagnostics);
+                }
+                for (name, count) in initial_counts {
+                    initial_solution.insert(Species::Name(name), Species::Count(count));
                 }
+                return Ok((thresholds, diagnostics));
             }
-            Self::XML(_path) => unimplemented!(),
-            Self::JSON(_path) => unimplemented!(),
-            Self::Unsuported(file_type) => panic!("Unsupported file type: found {}, expects CSV", file_type), 
+            _ => return Err(vec![self.unsupported_input()]),
         }
     }
 
 
 
-    pub fn write_solution(&self, stable_solution: Vec<(String, f64)>) {
+    /// Writes the value of every species, as a row in a CSV file or a field of an object in a JSON file.
+    /// Returns an error if the file type cannot be written
+    pub fn write_solution(&self, stable_solution: Vec<(String, f64)>) -> Result<(), String> {
         match self {
             Self::CSV(path) => {
-                let mut output_file = csv::WriterBuilder::new().from_path(path).unwrap();
+                let mut output_file = csv::WriterBuilder::new().from_path(path).map_err(|error| Self::write_error(path, error))?;
                 
                 for entry in stable_solution  {
-                    output_file.write_record([entry.0, entry.1.to_string()]).unwrap();
+                    output_file.write_record([entry.0, entry.1.to_string()]).map_err(|error| Self::write_error(path, error))?;
+                }
+                output_file.flush().map_err(|error| Self::write_error(path, error))?;
+            },
+            Self::JSON(path) => {
+                let species: serde_json::Map<String, serde_json::Value> = stable_solution.into_iter()
+                    .map(|(name, value)| (name, serde_json::json!(value)))
+                    .collect();
+                Self::write_json(path, &serde_json::Value::Object(species))?;
+            },
+            _ => return Err(self.unsupported_output()),
+        }
+        return Ok(());
+    }
+
+    // describes a file type which results cannot be written to
+    fn unsupported_output(&self) -> String {
+        let (Self::CSV(path) | Self::JSON(path) | Self::XML(path) | Self::Unsuported(path)) = self;
+        return format!("unsupported output format for {}, expected a CSV or JSON file", path);
+    }
+
+    /// Writes the per species statistics and quantiles of a simulation report with one row, or object, per species.
+    /// If any thresholds were set the probability of each is written after the species, under its own header in a CSV file.
+    /// Returns an error if the file type cannot be written
+    pub fn write_report<C: Count>(&self, report: &SimulationReport<C>, quantiles: &[f64]) -> Result<(), String> {
+        match self {
+            Self::CSV(path) => {
+                let mut output_file = csv::WriterBuilder::new().flexible(true).from_path(path).map_err(|error| Self::write_error(path, error))?;
+
+                let mut header: Vec<String> = ["species", "mean", "variance", "standard_error", "min", "max"].iter()
+                    .map(|field| field.to_string())
+                    .collect();
+                header.extend(quantiles.iter().map(|level| format!("q{}", level)));
+                output_file.write_record(header).map_err(|error| Self::write_error(path, error))?;
+
+                for ((name, statistics), distribution) in report.get_species().iter().zip(report.get_distributions()) {
+                    let mut record = vec![
+                        name.clone(),
+                        statistics.get_mean().to_string(),
+                        statistics.get_variance().to_string(),
+                        statistics.get_standard_error().to_string(),
+                        statistics.get_min().to_string(),
+                        statistics.get_max().to_string(),
+                    ];
+                    record.extend(quantiles.iter().map(|level| distribution.get_quantile(*level).to_string()));
+                    output_file.write_record(record).map_err(|error| Self::write_error(path, error))?;
+                }
+
+                if !report.get_thresholds().is_empty() {
+                    output_file.write_record(["threshold", "trials_reached", "probability"]).map_err(|error| Self::write_error(path, error))?;
+                    for (index, (threshold, trials_reached)) in report.get_thresholds().iter().enumerate() {
+                        output_file.write_record([
+                            threshold.clone(),
+                            trials_reached.to_string(),
+                            report.get_threshold_probability(index).to_string(),
+                        ]).map_err(|error| Self::write_error(path, error))?;
+                    }
+                }
+
+                if !report.is_complete() {
+                    output_file.write_record(["complete", "trials_finished"]).map_err(|error| Self::write_error(path, error))?;
+                    output_file.write_record(["false".to_string(), report.get_num_trials().to_string()]).map_err(|error| Self::write_error(path, error))?;
+                }
+
+                if let Some(confidence) = report.get_confidence() {
+                    output_file.write_record(["precision_target", "target_half_width", "half_width", "confidence"]).map_err(|error| Self::write_error(path, error))?;
+                    for result in report.get_precision() {
+                        output_file.write_record([
+                            result.species.clone(),
+                            result.target.to_string(),
+                            result.half_width.to_string(),
+                            confidence.to_string(),
+                        ]).map_err(|error| Self::write_error(path, error))?;
+                    }
                 }
+                output_file.flush().map_err(|error| Self::write_error(path, error))?;
             },
-            Self::JSON(_path) => todo!(), // implement JSON writing
-            Self::XML(_path) => todo!(), // implement XML writing
-            Self::Unsuported(other_file_type) => panic!("tried to write unsuported file type {}", other_file_type),
+            Self::JSON(path) => {
+                let mut species = serde_json::Map::new();
+                for ((name, statistics), distribution) in report.get_species().iter().zip(report.get_distributions()) {
+                    species.insert(name.clone(), serde_json::json!({
+                        "mean": statistics.get_mean(),
+                        "variance": statistics.get_variance(),
+                        "standard_error": statistics.get_standard_error(),
+                        "min": statistics.get_min().to_json(),
+                        "max": statistics.get_max().to_json(),
+                        "quantiles": Self::json_quantiles(distribution, quantiles),
+                    }));
+                }
+                let thresholds: Vec<serde_json::Value> = report.get_thresholds().iter().enumerate()
+                    .map(|(index, (threshold, trials_reached))| serde_json::json!({
+                        "threshold": threshold,
+                        "trials_reached": trials_reached,
+                        "probability": report.get_threshold_probability(index),
+                    }))
+                    .collect();
+                let precision = report.get_confidence().map(|confidence| serde_json::json!({
+                    "confidence": confidence,
+                    "targets_met": report.precision_targets_met(),
+                    "targets": report.get_precision().iter()
+                        .map(|result| serde_json::json!({
+                            "species": result.species,
+                            "target_half_width": result.target,
+                            "half_width": result.half_width,
+                        }))
+                        .collect::<Vec<serde_json::Value>>(),
+                }));
+                let json = serde_json::json!({
+                    "num_trials": report.get_num_trials(),
+                    "complete": report.is_complete(),
+                    "distinct_outcomes": report.get_outcomes().len(),
+                    "species": species,
+                    "thresholds": thresholds,
+                    "precision": precision,
+                });
+                Self::write_json(path, &json)?;
+            },
+            _ => return Err(self.unsupported_output()),
         }
+        return Ok(());
+    }
+
+    /// Writes the histogram of every species' final counts along with its quantiles.
+    /// CSV files get one row per bin, JSON files get one object per species.
+    /// Returns an error if the file type cannot be written
+    pub fn write_histograms<C: Count>(&self, report: &SimulationReport<C>, settings: &ReportSettings) -> Result<(), String> {
+        match self {
+            Self::CSV(path) => {
+                let mut output_file = csv::WriterBuilder::new().from_path(path).map_err(|error| Self::write_error(path, error))?;
+
+                output_file.write_record(["species", "lower", "upper", "count", "frequency"]).map_err(|error| Self::write_error(path, error))?;
+                for ((name, _), distribution) in report.get_species().iter().zip(report.get_distributions()) {
+                    for bin in distribution.get_histogram(settings.bins) {
+                        output_file.write_record([
+                            name.clone(),
+                            bin.lower.to_string(),
+                            bin.upper.to_string(),
+                            bin.count.to_string(),
+                            (bin.count as f64 / distribution.get_samples() as f64).to_string(),
+                        ]).map_err(|error| Self::write_error(path, error))?;
+                    }
+                }
+                output_file.flush().map_err(|error| Self::write_error(path, error))?;
+            },
+            Self::JSON(path) => {
+                let mut species = serde_json::Map::new();
+                for ((name, _), distribution) in report.get_species().iter().zip(report.get_distributions()) {
+                    let histogram: Vec<serde_json::Value> = distribution.get_histogram(settings.bins).iter()
+                        .map(|bin| serde_json::json!({"lower": bin.lower.to_json(), "upper": bin.upper.to_json(), "count": bin.count}))
+                        .collect();
+                    species.insert(name.clone(), serde_json::json!({
+                        "quantiles": Self::json_quantiles(distribution, &settings.quantiles),
+                        "histogram": histogram,
+                    }));
+                }
+                let json = serde_json::json!({
+                    "num_trials": report.get_num_trials(),
+                    "species": species,
+                });
+                Self::write_json(path, &json)?;
+            },
+            _ => return Err(self.unsupported_output()),
+        }
+        return Ok(());
+    }
+
+    // maps each quantile level to the quantile of a distribution
+    fn json_quantiles<C: Count>(distribution: &SpeciesDistribution<C>, quantiles: &[f64]) -> serde_json::Value {
+        let mut json_quantiles = serde_json::Map::new();
+        for level in quantiles {
+            json_quantiles.insert(level.to_string(), distribution.get_quantile(*level).to_json());
+        }
+        return serde_json::Value::Object(json_quantiles);
+    }
+
+    // writes a JSON value to a new file, describing any IO error with the path of the file
+    fn write_json(path: &str, json: &serde_json::Value) -> Result<(), String> {
+        let output_file = std::fs::File::create(path).map_err(|error| Self::write_error(path, error))?;
+        return serde_json::to_writer_pretty(output_file, json).map_err(|error| Self::write_error(path, error));
+    }
+
+    // describes an error which stopped results from being written to a file
+    fn write_error<E: std::fmt::Display>(path: &str, error: E) -> String {
+        return format!("could not write {}: {}", path, error);
+    }
+
+    /// Writes a deterministic trajectory with a header of species names and one row per recorded time,
+    /// or as a JSON list with one object per recorded time. Returns an error if the file type cannot be written
+    pub fn write_trajectory(&self, trajectory: &OdeTrajectory) -> Result<(), String> {
+        match self {
+            Self::CSV(path) => {
+                let mut output_file = csv::WriterBuilder::new().from_path(path).map_err(|error| Self::write_error(path, error))?;
+
+                let mut header = vec!["time".to_string()];
+                header.extend(trajectory.get_species().iter().cloned());
+                output_file.write_record(header).map_err(|error| Self::write_error(path, error))?;
+
+                for (time, state) in trajectory.get_times().iter().zip(trajectory.get_states()) {
+                    let mut record = vec![time.to_string()];
+                    record.extend(state.iter().map(|concentration| concentration.to_string()));
+                    output_file.write_record(record).map_err(|error| Self::write_error(path, error))?;
+                }
+                output_file.flush().map_err(|error| Self::write_error(path, error))?;
+            },
+            Self::JSON(path) => {
+                let entries: Vec<serde_json::Value> = trajectory.get_times().iter().zip(trajectory.get_states())
+                    .map(|(time, state)| {
+                        let mut entry = serde_json::Map::new();
+                        entry.insert("time".to_string(), serde_json::json!(time));
+                        for (name, concentration) in trajectory.get_species().iter().zip(state) {
+                            entry.insert(name.clone(), serde_json::json!(concentration));
+                        }
+                        serde_json::Value::Object(entry)
+                    })
+                    .collect();
+                Self::write_json(path, &serde_json::Value::Array(entries))?;
+            },
+            _ => return Err(self.unsupported_output()),
+        }
+        return Ok(());
+    }
+
+    /// Writes a probability distribution with a header of species names and one row per outcome,
+    /// or as a JSON list with one object per outcome. Returns an error if the file type cannot be written
+    pub fn write_distribution(&self, distribution: &ProbabilityDistribution) -> Result<(), String> {
+        match self {
+            Self::CSV(path) => {
+                let mut output_file = csv::WriterBuilder::new().from_path(path).map_err(|error| Self::write_error(path, error))?;
+
+                let mut header = distribution.get_species().to_vec();
+                header.push("probability".to_string());
+                output_file.write_record(header).map_err(|error| Self::write_error(path, error))?;
+
+                for (state, probability) in distribution.get_outcomes() {
+                    let mut record: Vec<String> = state.iter().map(|count| count.to_string()).collect();
+                    record.push(probability.to_string());
+                    output_file.write_record(record).map_err(|error| Self::write_error(path, error))?;
+                }
+                output_file.flush().map_err(|error| Self::write_error(path, error))?;
+            },
+            Self::JSON(path) => {
+                let outcomes: Vec<serde_json::Value> = distribution.get_outcomes().iter()
+                    .map(|(state, probability)| {
+                        let mut outcome = serde_json::Map::new();
+                        for (name, count) in distribution.get_species().iter().zip(state) {
+                            outcome.insert(name.clone(), serde_json::json!(count));
+                        }
+                        outcome.insert("probability".to_string(), serde_json::json!(probability));
+                        serde_json::Value::Object(outcome)
+                    })
+                    .collect();
+                Self::write_json(path, &serde_json::Value::Array(outcomes))?;
+            },
+            _ => return Err(self.unsupported_output()),
+        }
+        return Ok(());
     }
 }
 
@@ -192,82 +371,81 @@ enum WriterType {
 }
 
 impl WriterType {
-    fn from(file: &SupportedFileType, id: usize) -> Self {
+    fn from(file: &SupportedFileType, id: usize) -> Result<Self, String> {
         match file {
             SupportedFileType::CSV(path) => {
                 let mut path_with_id = id.to_string();
-                path_with_id.push_str(&path);
+                path_with_id.push_str(path);
 
-                return WriterType::CSV{
-                    writer: csv::WriterBuilder::new()
-                        .flexible(true)
-                        .from_path(path_with_id).unwrap(),
-                    header_written: false
-                }
+                let writer = csv::WriterBuilder::new()
+                    .flexible(true)
+                    .from_path(&path_with_id)
+                    .map_err(|error| format!("could not create timeline file {}: {}", path_with_id, error))?;
+                return Ok(WriterType::CSV{writer, header_written: false});
             }   
-            _ => unimplemented!()
+            SupportedFileType::JSON(path) | SupportedFileType::XML(path) | SupportedFileType::Unsuported(path) => {
+                return Err(format!("unsupported timeline format for {}, expected a CSV file", path));
+            }
         }
     }
 }
-pub struct TimelineWriter {
-    timeline_file: SupportedFileType,
-    temp_sub_files: HashMap<usize, WriterType>,
-    step_stream: Receiver<(Solution, usize)>,
+/// The number of timeline entries a trial buffers before writing them
+const TIMELINE_BATCH_SIZE: usize = 1024;
+
+/// Writes the timeline of a single trial to its own file, named by prefixing the timeline path with the trial id.
+/// Every entry is the simulated time, the reaction fired by the step, and the count of every species.
+/// Entries are buffered and written in batches by the trial's own thread,
+/// so trials writing timelines never wait on each other or on the engine.
+/// Any buffered entries are written when the writer is dropped, an error writing them then is ignored.
+pub struct TimelineWriter<C: Count = u64> {
+    writer: WriterType,
+    species: Vec<String>,
+    entries: Vec<(Vec<C>, f64, Option<String>)>,
 }
 
-impl TimelineWriter {
-    pub fn new(file: SupportedFileType, step_stream: Receiver<(Solution, usize)>) -> Self {
-        let temp_sub_files = HashMap::new();
+impl<C: Count> TimelineWriter<C> {
+    /// Creates the timeline file of the trial with some id, returns an error if it is not a CSV file or cannot be created
+    pub fn new(file: &SupportedFileType, id: usize, species: &[String]) -> Result<Self, String> {
+        return Ok(TimelineWriter {writer: WriterType::from(file, id)?, species: species.to_vec(), entries: Vec::with_capacity(TIMELINE_BATCH_SIZE)});
+    }
 
-        return TimelineWriter {timeline_file: file, temp_sub_files, step_stream};
+    /// buffers the species counts of a single step along with the reaction it fired, if it fired a single reaction,
+    /// writing the buffer once it is full
+    pub fn push(&mut self, counts: Vec<C>, simulated_time: f64, reaction: Option<String>) -> Result<(), String> {
+        self.entries.push((counts, simulated_time, reaction));
+        if self.entries.len() >= TIMELINE_BATCH_SIZE {
+            return self.flush();
+        }
+        return Ok(());
     }
-    pub fn begin_listen(mut self) {
-        loop {
-            match self.step_stream.recv() {
-                Ok((solution, id)) => {
-
-                    // ensure that a wiriter has been generated for given ID
-                    if !self.temp_sub_files.contains_key(&id) {
-                        self.temp_sub_files.insert(id, WriterType::from(&self.timeline_file, id));
+
+    /// writes every buffered entry, returns an error if the timeline file cannot be written
+    pub fn flush(&mut self) -> Result<(), String> {
+        let write_error = |error: csv::Error| format!("could not write timeline: {}", error);
+        match &mut self.writer {
+            WriterType::CSV{writer, header_written} => {
+                for (counts, simulated_time, reaction) in self.entries.drain(..) {
+                    // species names are only written as a header before the first entry
+                    if !*header_written {
+                        let mut names = vec!["time".to_string(), "reaction".to_string()];
+                        names.extend(self.species.iter().cloned());
+                        writer.write_record(names).map_err(write_error)?;
+                        *header_written = true;
                     }
 
-                    // Write data with writer at given ID
-                    self.temp_sub_files.entry(id)
-                    .and_modify(|sub_file|match sub_file {
-                        WriterType::CSV{writer,header_written} => {
-
-                            // only write names if there is no names header field in the sub file
-                            if !*header_written {
-                                let mut names = Vec::new();
-                                let mut counts = Vec::new();
-                                for (species_name, species_count) in solution.into_iter() {
-                                    names.push(species_name.to_string());
-                                    counts.push(species_count.to_string());
-                                }
-                                writer.write_record(names).unwrap();
-                                writer.write_record(counts).unwrap();
-                                writer.flush().unwrap();
-                                *header_written = true;
-                            } 
-
-                            // otherwise just ignore them completely and write the species as a new record
-                            else {
-                                let mut counts = Vec::new();
-                                for (_species_name, species_count) in solution.into_iter() {
-                                    counts.push(species_count.to_string());
-                                }
-                                writer.write_record(counts).unwrap();
-                                writer.flush().unwrap();
-                            }
-                        }
-                    });
-                }
-                Err(_msg) => {
-                    println!("Stream to Timeline Writer dropped\nShutting down...");
-                    // combining files is unimplemented
-                    return;
+                    let mut record = vec![simulated_time.to_string(), reaction.unwrap_or_default()];
+                    record.extend(counts.iter().map(|count| count.to_string()));
+                    writer.write_record(record).map_err(write_error)?;
                 }
+                writer.flush().map_err(|error| format!("could not write timeline: {}", error))?;
             }
         }
+        return Ok(());
     }
-}
\ No newline at end of file
+}
+
+impl<C: Count> Drop for TimelineWriter<C> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
@@ -5,84 +5,149 @@
 /// # Description
 /// This code provides a Trial struct and its implementation for simulating a reaction network.
 ///  The ReactionNetwork module contains all the necessary details about this network, including species, reactions, and terms.
-/// The simulation process is performed using the Simulate() function in the implementation of Trial. 
-/// It takes a mutable reference to a trial, evaluates its current status, and runs reactions until the network reaches a stable state.
+/// The simulation process is performed using the simulate() function in the implementation of Trial. 
+/// It evaluates the current status of the trial and runs reactions until the network reaches a stable state or one of the trial's thresholds.
 /// 
 /// # Usage
-/// To use this code, you need to import the ReactionNetwork module and create an instance of ReactionNetwork<'trial>, then create an instance of Trial by passing this instance as an argument.
-/// You can then run simulations on this Trial instance using the simulate() function.
-/// It returns a HashMap containing all the species keyd by their references in the stable network solution.
+/// To use this code, create an instance of ReactionNetwork, then create an instance of Trial by passing this instance, the trial's id, and the seed of its ensemble as arguments.
+/// You can then run simulations on this Trial instance using the simulate() function, typically on a worker thread.
+/// It sends a TrialResult over the given channel holding the count of every species in the stable solution, or why the trial stopped early.
 
-use reaction_network::{ReactionNetwork, reaction::term::solution::{Solution}};
+use reaction_network::{ReactionNetwork, count::Count};
 use results::TrialResult;
-use std::sync::mpsc::SyncSender;
+use threshold::Threshold;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc::Sender};
+use crate::supported_file_type::TimelineWriter;
 
 pub mod reaction_network; 
 pub mod results;
+pub mod threshold;
 
-pub struct Trial {
-    reaction_network: ReactionNetwork,
+pub struct Trial<C: Count = u64> {
+    reaction_network: ReactionNetwork<C>,
     stability: Stability, 
     max_semi_stable_steps: i32,
+    thresholds: Vec<(Threshold, Option<usize>)>,
+    reached_thresholds: Vec<usize>,
+    cancelled: Arc<AtomicBool>,
     id: usize,
 }
 
-impl <'trial_runtime> Trial {
+impl<C: Count> Trial<C> {
 
-    pub fn from(reaction_network: ReactionNetwork, max_semi_stable_steps_setting: Option<i32>, id: usize) -> Self {
-        let max_semi_stable_steps: i32; 
-        if let Some(number) = max_semi_stable_steps_setting {
-            max_semi_stable_steps = number;
-        } else {
-            max_semi_stable_steps = 99;
-        }
+    /// Creates a trial which draws its random numbers from its own stream, derived from the master seed of its ensemble and its id,
+    /// so a seeded ensemble is reproducible regardless of which thread runs which trial
+    pub fn from(mut reaction_network: ReactionNetwork<C>, max_semi_stable_steps_setting: Option<i32>, id: usize, seed: u64) -> Self {
+        let max_semi_stable_steps = max_semi_stable_steps_setting.unwrap_or(99);
+        reaction_network.set_seed(trial_seed(seed, id));
         
         Self {
             reaction_network,
             stability: Stability::Initial,
             max_semi_stable_steps,
+            thresholds: Vec::new(),
+            reached_thresholds: Vec::new(),
+            cancelled: Arc::new(AtomicBool::new(false)),
             id, 
         }
     }
 
-    pub fn simulate_with_timeline (&mut self, trial_tx: SyncSender<TrialResult>)  {
+    pub fn get_id(&self) -> usize {
+        return self.id;
+    }
+
+    /// Sets the thresholds which stop this trial as soon as any of them is reached, the reached thresholds are sent with its result.
+    /// Each threshold is paired with the index of its species, species outside the network always have a count of 0
+    pub fn set_thresholds(&mut self, thresholds: Vec<Threshold>) {
+        let network = self.reaction_network.get_network();
+        self.thresholds = thresholds.into_iter()
+            .map(|threshold| {
+                let index = network.get_species_index(&threshold.get_species_name().to_string());
+                (threshold, index)
+            })
+            .collect();
+    }
+
+    /// Sets the token which stops this trial once it is set, shared by every trial of a run.
+    /// It is checked before every step, and a cancelled trial stops without a result
+    pub fn set_cancellation_token(&mut self, cancelled: Arc<AtomicBool>) {
+        self.cancelled = cancelled;
+    }
+
+    /// Simulates the trial while writing its solution after every step to its own timeline file,
+    /// created by the engine for the id of the trial. The timeline is written in batches by the trial itself,
+    /// a trial whose timeline cannot be written fails with the error
+    // a send only fails once the engine has stopped listening, in which case the trial simply stops
+    pub fn simulate_with_timeline (&mut self, trial_tx: Sender<TrialResult<C>>, mut timeline: TimelineWriter<C>)  {
         let mut step_count = 0; 
         loop{
+            if self.cancelled.load(Ordering::Relaxed) {
+                let _ = trial_tx.send(TrialResult::Cancelled(self.id));
+                return;
+            }
             step_count += 1; 
-            self.step();
-            trial_tx.send(TrialResult::TimelineEntry(self.reaction_network.get_solution().clone(), self.id))
-                .expect("Reciever thread for trial {} dropped\nShutting down...");
+            if let Err(error) = self.step() {
+                let _ = timeline.flush();
+                let _ = trial_tx.send(TrialResult::Failed(error, self.id));
+                return;
+            }
+            let reaction = self.reaction_network.get_last_reaction().map(|index| self.reaction_network.describe_reaction(index));
+            let mut written = timeline.push(self.reaction_network.get_counts().to_vec(), self.reaction_network.get_simulated_time(), reaction);
+            if let (Ok(()), Stability::Stable) = (&written, &self.stability) {
+                written = timeline.flush();
+            }
+            if let Err(error) = written {
+                let _ = trial_tx.send(TrialResult::Failed(error, self.id));
+                return;
+            }
             if let Stability::Stable = self.stability {
-                trial_tx.send(TrialResult::StableSolution(self.reaction_network.get_solution().clone(), step_count))
-                .expect("Reciever thread for trial {} dropped\nShutting down...");
+                let _ = trial_tx.send(TrialResult::StableSolution(self.reaction_network.get_counts().to_vec(), step_count, self.reaction_network.get_simulated_time(), self.reached_thresholds.clone(), self.id));
                 return;
             }
         }   
     }
 
-    pub fn simulate(&mut self, trial_tx: SyncSender<TrialResult>) {
+    /// Simulates the trial until it is stable and sends its result over a buffered channel, so the trial never waits on the engine.
+    /// A trial whose reactions would take a count out of range stops with an error naming the reaction
+    pub fn simulate(&mut self, trial_tx: Sender<TrialResult<C>>) {
         let mut step_count = 0; 
         loop{
+            if self.cancelled.load(Ordering::Relaxed) {
+                let _ = trial_tx.send(TrialResult::Cancelled(self.id));
+                return;
+            }
             step_count += 1; 
-            self.step();
+            if let Err(error) = self.step() {
+                let _ = trial_tx.send(TrialResult::Failed(error, self.id));
+                return;
+            }
             if let Stability::Stable = self.stability {
-                trial_tx.send(TrialResult::StableSolution(self.reaction_network.get_solution().clone(), step_count))
-                .expect("Reciever thread for trial {} dropped\nShutting down...");
+                let _ = trial_tx.send(TrialResult::StableSolution(self.reaction_network.get_counts().to_vec(), step_count, self.reaction_network.get_simulated_time(), self.reached_thresholds.clone(), self.id));
                 return;
             }
         }   
     }
 
-    fn step(&mut self) {
+    // Fires the next reaction and updates the stability of the trial,
+    // a trial is never stable while delayed reactions have products waiting to be released
+    fn step(&mut self) -> Result<(), String> {
+        // a trial which starts with a threshold already reached never reacts
+        if let Stability::Initial = self.stability {
+            if self.thresholds_reached() {
+                self.stability = Stability::Stable;
+                return Ok(());
+            }
+        }
+
         match self.stability {
             Stability::Initial => {
-                self.reaction_network.react();
+                self.reaction_network.react()?;
 
-                if self.reaction_network.get_possible_reactions().is_empty() {
+                if self.reaction_network.get_possible_reactions().is_empty() && !self.reaction_network.has_pending_products() {
                     self.stability = Stability::Stable;
                 }
 
-                else if self.reaction_network.get_possible_reactions().is_subset(&self.reaction_network.get_null_adjacent_reactions()) {
+                else if self.reaction_network.get_possible_reactions().is_subset(self.reaction_network.get_null_adjacent_reactions()) && !self.reaction_network.has_pending_products() {
                     self.stability = Stability::SemiStable(0);
                 } 
                 
@@ -92,13 +157,13 @@ impl <'trial_runtime> Trial {
             } 
 
             Stability::Unstable => {
-                self.reaction_network.react();
+                self.reaction_network.react()?;
 
-                if self.reaction_network.get_possible_reactions().is_empty() {
+                if self.reaction_network.get_possible_reactions().is_empty() && !self.reaction_network.has_pending_products() {
                     self.stability = Stability::Stable;
                 }
 
-                else if self.reaction_network.get_possible_reactions().is_subset(self.reaction_network.get_null_adjacent_reactions()) {
+                else if self.reaction_network.get_possible_reactions().is_subset(self.reaction_network.get_null_adjacent_reactions()) && !self.reaction_network.has_pending_products() {
                     self.stability = Stability::SemiStable(0);
                 }
 
@@ -108,19 +173,19 @@ impl <'trial_runtime> Trial {
             }
 
             Stability::SemiStable(count) => {
-                self.reaction_network.react();
+                self.reaction_network.react()?;
 
-                if self.reaction_network.get_possible_reactions().is_empty() {
+                if self.reaction_network.get_possible_reactions().is_empty() && !self.reaction_network.has_pending_products() {
                     self.stability = Stability::Stable;
 
 
-                } else if self.reaction_network.get_possible_reactions().is_subset(self.reaction_network.get_null_adjacent_reactions()) && count < self.max_semi_stable_steps {
-                        self.reaction_network.react();
+                } else if self.reaction_network.get_possible_reactions().is_subset(self.reaction_network.get_null_adjacent_reactions()) && !self.reaction_network.has_pending_products() && count < self.max_semi_stable_steps {
+                        self.reaction_network.react()?;
                         self.stability = Stability::SemiStable(count + 1);
                 
 
-                } else if self.reaction_network.get_possible_reactions().is_subset(self.reaction_network.get_null_adjacent_reactions()) && count >= self.max_semi_stable_steps {
-                        self.reaction_network.react();
+                } else if self.reaction_network.get_possible_reactions().is_subset(self.reaction_network.get_null_adjacent_reactions()) && !self.reaction_network.has_pending_products() && count >= self.max_semi_stable_steps {
+                        self.reaction_network.react()?;
                         self.stability = Stability::Stable;
                 
 
@@ -133,12 +198,63 @@ impl <'trial_runtime> Trial {
                 self.stability = Stability::Stable;
             }
         }
+
+        // a trial stops as soon as any threshold is reached
+        if self.thresholds_reached() {
+            self.stability = Stability::Stable;
+        }
+        return Ok(());
+    }
+
+    // Records which thresholds the current solution reaches, returns true if any are reached
+    fn thresholds_reached(&mut self) -> bool {
+        let counts = self.reaction_network.get_counts();
+        self.reached_thresholds = self.thresholds.iter().enumerate()
+            .filter(|(_, (threshold, index))| match index {
+                Some(index) => threshold.is_satisfied_by(&counts[*index]),
+                None => threshold.is_satisfied_by(&C::from_u64(0)),
+            })
+            .map(|(index, _)| index)
+            .collect();
+        return !self.reached_thresholds.is_empty();
     }
 }
 
+/// Derives the seed of a single trial from the master seed of an ensemble and the id of the trial.
+/// The id is mixed in with SplitMix64 so that neighbouring trials get statistically independent streams.
+pub fn trial_seed(seed: u64, id: usize) -> u64 {
+    let mut z = seed.wrapping_add((id as u64).wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return z ^ (z >> 31);
+}
+
 enum Stability {
     Initial, 
     Unstable,
     SemiStable(i32),
     Stable,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use reaction_network::parse_test_network;
+
+    #[test]
+    fn test_cancelled_trial_stops() {
+        // A => B, 1 and B => A, 1 never become stable
+        let network = parse_test_network("A => B, 1\nB => A, 1", "A, 5");
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut trial = Trial::from(network, None, 3, 0);
+        trial.set_cancellation_token(Arc::clone(&cancelled));
+
+        let (trial_tx, trial_rx) = channel();
+        let handle = std::thread::spawn(move|| trial.simulate(trial_tx));
+        cancelled.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+
+        assert!(matches!(trial_rx.recv(), Ok(TrialResult::Cancelled(3))));
+    }
+}
@@ -0,0 +1,261 @@
+/// # Description
+/// The compiled form of a reaction network which trials, and every other simulator, run on.
+/// Species names are interned once into an alphabetically ordered list and referred to by their index everywhere else,
+/// so the state of a network is simply a `Vec<u64>` of counts in the same order.
+/// Reactions are compiled into arrays of species indices and coefficients,
+/// and states are only mapped back to named `Solution`s when results are written.
+
+use std::collections::{BTreeSet, HashMap};
+use super::count::Count;
+use super::reaction::{Reaction, delay::Delay, label::Label, term::solution::{Solution, Species}};
+
+/// A single reaction compiled into index form
+/// - `rate`: the mass action rate constant
+/// - `reactants`: the index and coefficient of each reactant species, ordered by index
+/// - `products`: the index and coefficient of each product species, ordered by index
+/// - `net_changes`: the index and net change of each species altered by the reaction, ordered by index.
+///                  Species which appear as both reactant and product with the same coefficient (catalysts) are left out.
+/// - `initiation_changes`: the changes at the moment the reaction fires,
+///                         a delayed reaction only consumes its reactants until its products are released
+/// - `delay`: the delay between the reaction consuming its reactants and releasing its products, if any
+/// - `label`: the label of the reaction, if any, shared by both halves of a reversible reaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledReaction {
+    rate: f64,
+    reactants: Vec<(usize, u64)>,
+    products: Vec<(usize, u64)>,
+    net_changes: Vec<(usize, i64)>,
+    initiation_changes: Vec<(usize, i64)>,
+    delay: Option<Delay>,
+    label: Option<Label>,
+}
+
+impl CompiledReaction {
+
+    // compiles a reaction given the index of every species name
+    fn from(reaction: &Reaction, index_of: &HashMap<String, usize>) -> Self {
+        let compile_terms = |terms: &mut dyn Iterator<Item = (&Species, u64)>| {
+            let mut compiled: Vec<(usize, u64)> = terms
+                .map(|(species, coefficient)| (index_of[&species.to_string()], coefficient))
+                .collect();
+            compiled.sort_unstable();
+            return compiled;
+        };
+        let reactants = compile_terms(&mut reaction.get_reactants().iter().map(|term| (term.get_species_name(), term.get_coefficient())));
+        let products = compile_terms(&mut reaction.get_products().iter().map(|term| (term.get_species_name(), term.get_coefficient())));
+
+        let mut changes: HashMap<usize, i64> = HashMap::new();
+        for (index, coefficient) in &reactants {
+            *changes.entry(*index).or_insert(0) -= *coefficient as i64;
+        }
+        let mut initiation_changes: Vec<(usize, i64)> = changes.iter()
+            .map(|(index, change)| (*index, *change))
+            .collect();
+        for (index, coefficient) in &products {
+            *changes.entry(*index).or_insert(0) += *coefficient as i64;
+        }
+        let mut net_changes: Vec<(usize, i64)> = changes.into_iter()
+            .filter(|(_, change)| *change != 0)
+            .collect();
+        net_changes.sort_unstable();
+        initiation_changes.sort_unstable();
+
+        let delay = reaction.get_delay().copied();
+        if delay.is_none() {
+            initiation_changes = net_changes.clone();
+        }
+
+        let label = reaction.get_label().cloned();
+        return Self{rate: reaction.get_reaction_rate(), reactants, products, net_changes, initiation_changes, delay, label};
+    }
+
+    /// returns the mass action rate constant
+    pub fn get_rate(&self) -> f64 {
+        return self.rate;
+    }
+
+    /// returns the index and coefficient of each reactant species
+    pub fn get_reactants(&self) -> &[(usize, u64)] {
+        return &self.reactants;
+    }
+
+    /// returns the index and coefficient of each product species
+    pub fn get_products(&self) -> &[(usize, u64)] {
+        return &self.products;
+    }
+
+    /// returns the index and net change of every species altered by the reaction
+    pub fn get_net_changes(&self) -> &[(usize, i64)] {
+        return &self.net_changes;
+    }
+
+    /// returns the index and change of every species altered at the moment the reaction fires
+    pub fn get_initiation_changes(&self) -> &[(usize, i64)] {
+        return &self.initiation_changes;
+    }
+
+    /// returns the delay of this reaction, None if its products are released as soon as it fires
+    pub fn get_delay(&self) -> Option<&Delay> {
+        return self.delay.as_ref();
+    }
+
+    /// returns the label of this reaction, None if it has none
+    pub fn get_label(&self) -> Option<&Label> {
+        return self.label.as_ref();
+    }
+
+    /// returns true if every reactant has a count of at least its coefficient
+    pub fn is_possible<C: Count>(&self, counts: &[C]) -> bool {
+        return self.reactants.iter().all(|(index, coefficient)| counts[*index].saturating_to_u64() >= *coefficient);
+    }
+
+    /// Returns the mass action propensity `rate * C(count, coefficient)` multiplied over all reactants
+    pub fn get_propensity<C: Count>(&self, counts: &[C]) -> f64 {
+        let mut propensity = self.rate;
+        for (index, coefficient) in &self.reactants {
+            propensity *= counts[*index].choose(*coefficient);
+        }
+        return propensity;
+    }
+}
+
+/// A list of reactions compiled over a fixed, alphabetically ordered, list of every species they name
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledNetwork {
+    species: Vec<String>,
+    reactions: Vec<CompiledReaction>,
+}
+
+impl CompiledNetwork {
+
+    /// compiles reactions, keeping the order they are given in
+    pub fn from(reactions: &[Reaction]) -> Self {
+        let names: BTreeSet<String> = reactions.iter()
+            .flat_map(|reaction| reaction.get_reactants().iter().chain(reaction.get_products()))
+            .map(|term| term.get_species_name().to_string())
+            .collect();
+        let species: Vec<String> = names.into_iter().collect();
+        let index_of: HashMap<String, usize> = species.iter().cloned()
+            .enumerate()
+            .map(|(index, name)| (name, index))
+            .collect();
+
+        let reactions = reactions.iter().map(|reaction| CompiledReaction::from(reaction, &index_of)).collect();
+        return Self{species, reactions};
+    }
+
+    /// returns the names of all species in the order used by counts
+    pub fn get_species(&self) -> &[String] {
+        return &self.species;
+    }
+
+    /// returns the index of a species, None if no reaction names it
+    pub fn get_species_index(&self, name: &str) -> Option<usize> {
+        return self.species.binary_search_by(|species| species.as_str().cmp(name)).ok();
+    }
+
+    /// returns every reaction in index order
+    pub fn get_reactions(&self) -> &[CompiledReaction] {
+        return &self.reactions;
+    }
+
+    /// returns the reaction at some index
+    pub fn get_reaction(&self, index: usize) -> &CompiledReaction {
+        return &self.reactions[index];
+    }
+
+    /// returns the index of the other half of the reversible reaction the reaction at some index belongs to,
+    /// None if it is not reversible
+    pub fn get_reverse(&self, index: usize) -> Option<usize> {
+        let label = self.reactions[index].label.as_ref()?;
+        return self.reactions.iter().position(|reaction| reaction.label.as_ref().is_some_and(|other| label.is_reverse_of(other)));
+    }
+
+    /// returns the reaction at some index by its label, or written with species names if it has none,
+    /// e.g. `r1`, `X + Y <=> 2 B, reverse`, or `X + Y => 2 B`
+    pub fn describe_reaction(&self, index: usize) -> String {
+        let side = |terms: &[(usize, u64)]| terms.iter()
+            .map(|(species, coefficient)| match coefficient {
+                1 => self.species[*species].clone(),
+                _ => format!("{} {}", coefficient, self.species[*species]),
+            })
+            .collect::<Vec<String>>()
+            .join(" + ");
+        let reaction = &self.reactions[index];
+        return match &reaction.label {
+            Some(label) => label.to_string(),
+            None => format!("{} => {}", side(&reaction.reactants), side(&reaction.products)).trim().to_string(),
+        };
+    }
+
+    /// returns the counts of a solution in species order, species missing from the solution have a count of 0
+    pub fn get_counts<C: Count>(&self, solution: &Solution) -> Vec<C> {
+        return self.species.iter()
+            .map(|name| match solution.species_counts.get(&Species::Name(name.clone())) {
+                Some(Species::Count(count)) => C::from_u64(*count),
+                _ => C::from_u64(0),
+            })
+            .collect();
+    }
+
+    /// maps counts in species order back to a named solution
+    pub fn get_solution(&self, counts: &[u64]) -> Solution {
+        return Solution{species_counts: self.species.iter()
+            .zip(counts)
+            .map(|(name, count)| (Species::Name(name.clone()), Species::Count(*count)))
+            .collect()};
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use super::super::reaction::term::Term;
+
+    #[test]
+    fn test_compile_reaction() {
+        // 2 A + C => B + C, 3 with C as a catalyst
+        let reaction = Reaction::new(
+            HashSet::from([Term::new(String::from("A"), 2), Term::new(String::from("C"), 1)]),
+            HashSet::from([Term::new(String::from("B"), 1), Term::new(String::from("C"), 1)]),
+            3.0,
+        );
+        let network = CompiledNetwork::from(&[reaction]);
+
+        assert_eq!(network.get_species(), &["A", "B", "C"]);
+        assert_eq!(network.get_species_index("C"), Some(2));
+        assert_eq!(network.get_species_index("D"), None);
+
+        let compiled = network.get_reaction(0);
+        assert_eq!(compiled.get_reactants(), &[(0, 2), (2, 1)]);
+        assert_eq!(compiled.get_net_changes(), &[(0, -2), (1, 1)]);
+        assert!(!compiled.is_possible(&[1_u64, 0, 1]));
+        // 3 * C(4, 2) * C(1, 1)
+        assert_eq!(compiled.get_propensity(&[4_u64, 0, 1]), 18.0);
+
+        assert_eq!(network.describe_reaction(0), "2 A + C => B + C");
+
+        let solution = network.get_solution(&[4, 0, 1]);
+        assert_eq!(network.get_counts::<u64>(&solution), vec![4, 0, 1]);
+    }
+
+    #[test]
+    fn test_reversible_pair() {
+        // A + B <=> C, 2, 1
+        let (forward, reverse) = Reaction::reversible(
+            HashSet::from([Term::new(String::from("A"), 1), Term::new(String::from("B"), 1)]),
+            HashSet::from([Term::new(String::from("C"), 1)]),
+            2.0,
+            1.0,
+        );
+        let network = CompiledNetwork::from(&[forward, reverse]);
+
+        assert_eq!(network.get_reverse(0), Some(1));
+        assert_eq!(network.get_reverse(1), Some(0));
+        assert_eq!(network.get_reaction(1).get_rate(), 1.0);
+        assert_eq!(network.get_reaction(1).get_net_changes(), &[(0, 1), (1, 1), (2, -1)]);
+        assert_eq!(network.describe_reaction(0), "A + B <=> C, forward");
+        assert_eq!(network.describe_reaction(1), "A + B <=> C, reverse");
+    }
+}
@@ -0,0 +1,202 @@
+/// # Description
+/// The types species counts may be stored as while a network is simulated.
+/// By default counts are `u64`, every update is checked and a reaction which would overflow or underflow a count
+/// stops the simulation with an error naming the reaction instead of silently wrapping.
+/// Networks with runaway growth may instead count with arbitrary precision `BigUint`s,
+/// which never overflow at the cost of slower updates.
+
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use super::reaction::binomial;
+
+/// A non negative species count
+pub trait Count: Clone + Ord + Hash + Debug + Display + Send + Sync + 'static {
+
+    fn from_u64(value: u64) -> Self;
+
+    /// converts an arbitrary precision count, None if it does not fit
+    fn from_biguint(value: &BigUint) -> Option<Self>;
+
+    fn to_biguint(&self) -> BigUint;
+
+    /// returns the count as a float, rounding counts too large to be represented exactly
+    fn to_f64(&self) -> f64;
+
+    /// returns the count, or u64::MAX if it is larger
+    fn saturating_to_u64(&self) -> u64;
+
+    /// returns `count choose k` as a float, 0 if k is larger than the count
+    fn choose(&self, k: u64) -> f64;
+
+    /// adds to the count, returns false leaving the count unchanged if it would overflow
+    fn try_add(&mut self, value: u64) -> bool;
+
+    /// subtracts from the count, returns false leaving the count unchanged if it would become negative
+    fn try_sub(&mut self, value: u64) -> bool;
+
+    /// applies a signed change to the count, returns false leaving the count unchanged if it would overflow or become negative
+    fn try_apply(&mut self, change: i128) -> bool {
+        let magnitude = change.unsigned_abs();
+        if magnitude > u64::MAX as u128 {
+            let mut updated = self.to_biguint();
+            if change < 0 {
+                if updated < BigUint::from(magnitude) {
+                    return false;
+                }
+                updated -= magnitude;
+            } else {
+                updated += magnitude;
+            }
+            return match Self::from_biguint(&updated) {
+                Some(updated) => {
+                    *self = updated;
+                    true
+                }
+                None => false,
+            };
+        }
+
+        return if change < 0 {
+            self.try_sub(magnitude as u64)
+        } else {
+            self.try_add(magnitude as u64)
+        };
+    }
+
+    /// returns the count as a JSON number, or a string if it is too large to be a JSON number
+    fn to_json(&self) -> serde_json::Value;
+}
+
+impl Count for u64 {
+
+    fn from_u64(value: u64) -> Self {
+        return value;
+    }
+
+    fn from_biguint(value: &BigUint) -> Option<Self> {
+        return value.to_u64();
+    }
+
+    fn to_biguint(&self) -> BigUint {
+        return BigUint::from(*self);
+    }
+
+    fn to_f64(&self) -> f64 {
+        return *self as f64;
+    }
+
+    fn saturating_to_u64(&self) -> u64 {
+        return *self;
+    }
+
+    fn choose(&self, k: u64) -> f64 {
+        return binomial(*self, k);
+    }
+
+    fn try_add(&mut self, value: u64) -> bool {
+        return match self.checked_add(value) {
+            Some(sum) => {
+                *self = sum;
+                true
+            }
+            None => false,
+        };
+    }
+
+    fn try_sub(&mut self, value: u64) -> bool {
+        return match self.checked_sub(value) {
+            Some(difference) => {
+                *self = difference;
+                true
+            }
+            None => false,
+        };
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        return serde_json::json!(*self);
+    }
+}
+
+impl Count for BigUint {
+
+    fn from_u64(value: u64) -> Self {
+        return BigUint::from(value);
+    }
+
+    fn from_biguint(value: &BigUint) -> Option<Self> {
+        return Some(value.clone());
+    }
+
+    fn to_biguint(&self) -> BigUint {
+        return self.clone();
+    }
+
+    fn to_f64(&self) -> f64 {
+        return ToPrimitive::to_f64(self).unwrap_or(f64::INFINITY);
+    }
+
+    fn saturating_to_u64(&self) -> u64 {
+        return self.to_u64().unwrap_or(u64::MAX);
+    }
+
+    fn choose(&self, k: u64) -> f64 {
+        if let Some(count) = self.to_u64() {
+            return binomial(count, k);
+        }
+
+        // k is far smaller than a count which does not fit in a u64
+        let count = Count::to_f64(self);
+        let mut combinations = 1.0;
+        for i in 0..k {
+            combinations *= (count - i as f64) / (i + 1) as f64;
+        }
+        return combinations;
+    }
+
+    fn try_add(&mut self, value: u64) -> bool {
+        *self += value;
+        return true;
+    }
+
+    fn try_sub(&mut self, value: u64) -> bool {
+        if *self < BigUint::from(value) {
+            return false;
+        }
+        *self -= value;
+        return true;
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        return match self.to_u64() {
+            Some(count) => serde_json::json!(count),
+            None => serde_json::json!(self.to_string()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_updates() {
+        let mut count: u64 = u64::MAX - 1;
+        assert!(count.try_add(1));
+        assert!(!count.try_add(1));
+        assert_eq!(count, u64::MAX);
+
+        let mut count: u64 = 2;
+        assert!(!count.try_apply(-3));
+        assert!(count.try_apply(-2));
+        assert_eq!(count, 0);
+
+        let mut big = BigUint::from(u64::MAX);
+        assert!(big.try_apply(i128::MAX));
+        assert_eq!(big, BigUint::from(u64::MAX) + BigUint::from(i128::MAX as u128));
+        assert_eq!(big.saturating_to_u64(), u64::MAX);
+        assert!(big.to_json().is_string());
+    }
+}
@@ -0,0 +1,95 @@
+/// A min heap of products waiting to be released by delayed reactions.
+///
+/// Every time a delayed reaction fires an entry is pushed holding the reaction's index and the simulated time
+/// at which its products should be added to the solution.
+/// Entries are popped in order of release time, ties are broken by reaction index so that releases are deterministic.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Pending products ordered by release time
+#[derive(Clone, Debug, Default)]
+pub struct DelayQueue {
+    pending: BinaryHeap<PendingProducts>,
+}
+
+impl DelayQueue {
+
+    /// creates an empty queue
+    pub fn new() -> Self {
+        return Self { pending: BinaryHeap::new() };
+    }
+
+    /// schedules the products of the reaction at some index to be released at `release_time`
+    pub fn push(&mut self, release_time: f64, reaction: usize) {
+        self.pending.push(PendingProducts { release_time, reaction });
+    }
+
+    /// returns the earliest release time of any pending products
+    pub fn peek_time(&self) -> Option<f64> {
+        return self.pending.peek().map(|pending| pending.release_time);
+    }
+
+    /// removes the earliest pending products and returns their release time and reaction index
+    pub fn pop(&mut self) -> Option<(f64, usize)> {
+        return self.pending.pop().map(|pending| (pending.release_time, pending.reaction));
+    }
+
+    /// returns the number of firings whose products are still pending
+    pub fn len(&self) -> usize {
+        return self.pending.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.pending.is_empty();
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PendingProducts {
+    release_time: f64,
+    reaction: usize,
+}
+
+// ordering is reversed so that the max heap `BinaryHeap` pops the earliest release first
+impl Ord for PendingProducts {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return other.release_time.total_cmp(&self.release_time)
+            .then_with(|| other.reaction.cmp(&self.reaction));
+    }
+}
+
+impl PartialOrd for PendingProducts {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl PartialEq for PendingProducts {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for PendingProducts {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_order() {
+        let mut queue = DelayQueue::new();
+        queue.push(3.0, 0);
+        queue.push(1.0, 2);
+        queue.push(1.0, 1);
+        queue.push(2.0, 0);
+
+        assert_eq!(queue.peek_time(), Some(1.0));
+        assert_eq!(queue.pop(), Some((1.0, 1)));
+        assert_eq!(queue.pop(), Some((1.0, 2)));
+        assert_eq!(queue.pop(), Some((2.0, 0)));
+        assert_eq!(queue.pop(), Some((3.0, 0)));
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+/// # Description
+/// Detection of reactions which are written more than once in an input file.
+/// Two reactions are duplicates if they have the same reactants, products, rate, and delay,
+/// and parallel if they only differ in their rate.
+/// Both fire the same change of counts, so they may be kept as separate channels,
+/// merged into one reaction whose rate is the sum of their rates, or rejected, as chosen with a `DuplicatePolicy`.
+
+use std::collections::BTreeMap;
+use super::reaction::Reaction;
+use crate::parser::{diagnostic::{Diagnostic, Severity}, tokenizer::tokenize};
+
+/// The hint given with every group of repeated reactions which is rejected
+pub const REJECT_HINT: &str = "repeated reactions may be kept with '--duplicates keep' or merged with '--duplicates merge'";
+
+/// The hint given with every group of repeated reactions which is kept
+pub const KEEP_HINT: &str = "they are kept as separate reactions, '--duplicates merge' merges them by summing their rates";
+
+/// Selects how duplicate and parallel reactions are handled when an input file is parsed
+/// - `Keep`: every reaction is kept as a separate channel
+/// - `Merge`: the reactions are merged into a single reaction whose rate is the sum of their rates
+/// - `Reject`: the input file is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    #[default]
+    Keep,
+    Merge,
+    Reject,
+}
+
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_ref() {
+            "keep" => Ok(DuplicatePolicy::Keep),
+            "merge" => Ok(DuplicatePolicy::Merge),
+            "reject" => Ok(DuplicatePolicy::Reject),
+            _ => Err(format!("Invalid duplicate policy '{}', expected one of \"keep\", \"merge\", \"reject\"", s)),
+        }
+    }
+}
+
+/// Returns every group of reactions which share their reactants, products, and delay, as indices into the reactions,
+/// in the order of the first reaction of each group
+pub fn find_repeated(reactions: &[(usize, Reaction)]) -> Vec<Vec<usize>> {
+    // group reactions by everything but their rate and label
+    let mut groups: BTreeMap<Reaction, Vec<usize>> = BTreeMap::new();
+    for (index, (_, reaction)) in reactions.iter().enumerate() {
+        let mut channel = reaction.clone();
+        channel.set_reaction_rate(0.0);
+        channel.set_label(None);
+        groups.entry(channel).or_default().push(index);
+    }
+    let mut repeated: Vec<Vec<usize>> = groups.into_values().filter(|group| group.len() > 1).collect();
+    repeated.sort();
+    return repeated;
+}
+
+/// Describes a group of repeated reactions with their line numbers, by their labels if every one is labelled,
+/// e.g. `duplicate reaction [A => B] with rate 1 on lines 1, 4`, `duplicate reactions 'r1', 'r4' with rate 1 on lines 1, 4`,
+/// or `parallel reactions [A => B] on lines 1, 4 with rates 1, 2`
+pub fn describe_repeated(reactions: &[(usize, Reaction)], group: &[usize]) -> String {
+    let lines = group.iter().map(|index| reactions[*index].0.to_string()).collect::<Vec<String>>().join(", ");
+    let rates: Vec<f64> = group.iter().map(|index| reactions[*index].1.get_reaction_rate()).collect();
+    let labels: Option<Vec<String>> = group.iter()
+        .map(|index| reactions[*index].1.get_label().map(|label| format!("'{}'", label)))
+        .collect();
+    let reaction = match &labels {
+        Some(labels) => labels.join(", "),
+        None => format!("[{}]", reactions[group[0]].1),
+    };
+
+    if rates.iter().all(|rate| *rate == rates[0]) {
+        let noun = if labels.is_some() { "reactions" } else { "reaction" };
+        return format!("duplicate {} {} with rate {} on lines {}", noun, reaction, rates[0], lines);
+    }
+    let rates = rates.iter().map(|rate| rate.to_string()).collect::<Vec<String>>().join(", ");
+    return format!("parallel reactions {} on lines {} with rates {}", reaction, lines, rates);
+}
+
+/// Applies a policy to reactions paired with the line of the file they were read from,
+/// returning the reactions which remain in the order they were read.
+/// Every group of duplicate or parallel reactions is returned as a warning at the line of its first repeat,
+/// if the policy is `Reject` every group is instead returned as an error
+pub fn resolve_duplicates(file: &str, text: &str, reactions: Vec<(usize, Reaction)>, policy: DuplicatePolicy) -> Result<(Vec<Reaction>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    // the merged rate of the first reaction of each merged group, None for the reactions merged into it
+    let mut merged_rates: BTreeMap<usize, Option<f64>> = BTreeMap::new();
+    let mut warnings: Vec<Diagnostic> = Vec::new();
+    let mut rejected: Vec<Diagnostic> = Vec::new();
+    let lines: Vec<&str> = text.lines().collect();
+    for group in find_repeated(&reactions) {
+        let line = reactions[group[1]].0;
+        let snippet = lines.get(line - 1).copied().unwrap_or_default();
+        let tokens = tokenize(snippet);
+        let (column, length) = match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => (first.get_column(), last.get_end() - first.get_column()),
+            _ => (1, 1),
+        };
+        let description = describe_repeated(&reactions, &group);
+
+        match policy {
+            DuplicatePolicy::Keep => {
+                let mut diagnostic = Diagnostic::new(Severity::Warning, file, line, column, length, snippet, description);
+                diagnostic.set_hint(Some(KEEP_HINT.to_string()));
+                warnings.push(diagnostic);
+            }
+            DuplicatePolicy::Merge => {
+                let rate: f64 = group.iter().map(|index| reactions[*index].1.get_reaction_rate()).sum();
+                let mut diagnostic = Diagnostic::new(Severity::Warning, file, line, column, length, snippet, description);
+                diagnostic.set_hint(Some(format!("they were merged into a single reaction with rate {}", rate)));
+                warnings.push(diagnostic);
+                merged_rates.insert(group[0], Some(rate));
+                for index in &group[1..] {
+                    merged_rates.insert(*index, None);
+                }
+            }
+            DuplicatePolicy::Reject => {
+                let mut diagnostic = Diagnostic::new(Severity::Error, file, line, column, length, snippet, description);
+                diagnostic.set_hint(Some(REJECT_HINT.to_string()));
+                rejected.push(diagnostic);
+            }
+        }
+    }
+
+    if !rejected.is_empty() {
+        return Err(rejected);
+    }
+
+    let reactions = reactions.into_iter()
+        .enumerate()
+        .filter_map(|(index, (_, mut reaction))| match merged_rates.get(&index) {
+            Some(Some(rate)) => {
+                reaction.set_reaction_rate(*rate);
+                Some(reaction)
+            }
+            Some(None) => None,
+            None => Some(reaction),
+        })
+        .collect();
+    return Ok((reactions, warnings));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::trial::reaction_network::reaction::term::Term;
+
+    fn reaction(reactant: &str, product: &str, rate: f64) -> Reaction {
+        return Reaction::new(HashSet::from([Term::new(reactant.to_string(), 1)]), HashSet::from([Term::new(product.to_string(), 1)]), rate);
+    }
+
+    #[test]
+    fn test_resolve_duplicates() {
+        // A => B is written twice with rate 1 and once with rate 2, B => C only once
+        let reactions = vec![
+            (1, reaction("A", "B", 1.0)),
+            (2, reaction("B", "C", 1.0)),
+            (3, reaction("A", "B", 1.0)),
+            (4, reaction("A", "B", 2.0)),
+        ];
+
+        let text = "A => B, 1\nB => C, 1\nA => B, 1\nA => B, 2\n";
+
+        let (kept, warnings) = resolve_duplicates("input.csv", text, reactions.clone(), DuplicatePolicy::Keep).unwrap();
+        assert_eq!(kept.len(), 4);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].get_severity(), Severity::Warning);
+        assert_eq!((warnings[0].get_line(), warnings[0].get_column()), (3, 1));
+        assert_eq!(warnings[0].get_message(), "parallel reactions [A => B] on lines 1, 3, 4 with rates 1, 1, 2");
+        assert_eq!(warnings[0].get_hint(), Some(KEEP_HINT));
+
+        let (merged, warnings) = resolve_duplicates("input.csv", text, reactions, DuplicatePolicy::Merge).unwrap();
+        assert_eq!(merged, vec![reaction("A", "B", 4.0), reaction("B", "C", 1.0)]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].get_severity(), Severity::Warning);
+        assert_eq!(warnings[0].get_hint(), Some("they were merged into a single reaction with rate 4"));
+    }
+
+    #[test]
+    fn test_reject_duplicates() {
+        let text = "A => B, 1\n\nA => B, 1\n";
+        let reactions = vec![(1, reaction("A", "B", 1.0)), (3, reaction("A", "B", 1.0))];
+
+        let rejected = resolve_duplicates("input.csv", text, reactions, DuplicatePolicy::Reject).unwrap_err();
+        assert_eq!(rejected.len(), 1);
+        assert!(rejected[0].is_error());
+        assert_eq!((rejected[0].get_line(), rejected[0].get_column()), (3, 1));
+        assert_eq!(rejected[0].get_message(), "duplicate reaction [A => B] with rate 1 on lines 1, 3");
+    }
+}
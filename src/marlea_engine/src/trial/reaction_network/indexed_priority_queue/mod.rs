@@ -0,0 +1,119 @@
+/// An indexed binary min heap of putative reaction firing times.
+///
+/// Each reaction index in `0..len` always has exactly one entry in the heap, and `positions` tracks where that entry lives
+/// so that the firing time of any single reaction can be changed in O(log R) without searching the heap.
+/// This is the priority queue described by Gibson and Bruck for the next reaction method.
+#[derive(Clone, Debug)]
+pub struct IndexedPriorityQueue {
+    // heap of (firing time, reaction index)
+    heap: Vec<(f64, usize)>,
+    // positions[reaction index] = position of that reaction within heap
+    positions: Vec<usize>,
+}
+
+impl IndexedPriorityQueue {
+
+    /// creates a new queue where the reaction at index `i` has firing time `times[i]`
+    pub fn from(times: Vec<f64>) -> Self {
+        let heap: Vec<(f64, usize)> = times.into_iter().enumerate().map(|(index, time)| (time, index)).collect();
+        let positions = (0..heap.len()).collect();
+        let mut queue = Self { heap, positions };
+
+        // heapify from the last parent down to the root
+        for position in (0..queue.heap.len() / 2).rev() {
+            queue.sift_down(position);
+        }
+
+        return queue;
+    }
+
+    /// returns the index and firing time of the reaction which will fire next
+    pub fn peek(&self) -> Option<(usize, f64)> {
+        return self.heap.first().map(|(time, index)| (*index, *time));
+    }
+
+    /// returns the current firing time of a reaction
+    pub fn get_time(&self, index: usize) -> f64 {
+        return self.heap[self.positions[index]].0;
+    }
+
+    /// changes the firing time of a reaction and restores the heap property
+    pub fn update(&mut self, index: usize, time: f64) {
+        let position = self.positions[index];
+        let old_time = self.heap[position].0;
+        self.heap[position].0 = time;
+
+        if time < old_time {
+            self.sift_up(position);
+        } else {
+            self.sift_down(position);
+        }
+    }
+
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if self.heap[position].0 < self.heap[parent].0 {
+                self.swap(position, parent);
+                position = parent;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let left = 2 * position + 1;
+            let right = left + 1;
+            let mut smallest = position;
+
+            if left < self.heap.len() && self.heap[left].0 < self.heap[smallest].0 {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.heap[right].0 < self.heap[smallest].0 {
+                smallest = right;
+            }
+
+            if smallest == position {
+                break;
+            }
+            self.swap(position, smallest);
+            position = smallest;
+        }
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.positions[self.heap[a].1] = a;
+        self.positions[self.heap[b].1] = b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peek_returns_minimum() {
+        let queue = IndexedPriorityQueue::from(vec![3.0, 1.0, f64::INFINITY, 2.0]);
+        assert_eq!(queue.peek(), Some((1, 1.0)));
+    }
+
+    #[test]
+    fn test_update() {
+        let mut queue = IndexedPriorityQueue::from(vec![3.0, 1.0, f64::INFINITY, 2.0]);
+
+        queue.update(1, 5.0);
+        assert_eq!(queue.peek(), Some((3, 2.0)));
+
+        queue.update(2, 0.5);
+        assert_eq!(queue.peek(), Some((2, 0.5)));
+        assert_eq!(queue.get_time(1), 5.0);
+
+        queue.update(2, f64::INFINITY);
+        queue.update(3, f64::INFINITY);
+        queue.update(0, f64::INFINITY);
+        assert_eq!(queue.peek(), Some((1, 5.0)));
+    }
+}
@@ -1,49 +1,179 @@
 use std::collections::HashSet;
-use rand::Rng;
-use reaction::{Reaction, term::solution::{Species, Solution}};
+use std::sync::Arc;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use reaction::{Reaction, term::solution::Solution};
+use compiled::{CompiledNetwork, CompiledReaction};
+use count::Count;
+use indexed_priority_queue::IndexedPriorityQueue;
+use delay_queue::DelayQueue;
 
-pub mod reaction; 
+pub mod reaction;
+pub mod compiled;
+pub mod count;
+pub mod indexed_priority_queue;
+pub mod delay_queue;
+pub mod duplicates;
+mod tau_leap;
+
+/// Selects the stochastic simulation algorithm a `ReactionNetwork` uses when `react()` is called
+/// - `Direct`: Gillespie's direct method, every step rescans all reactions and sums their propensities
+/// - `NextReaction`: Gibson and Bruck's next reaction method, every step only recomputes the reactions which depend on
+///                   the species changed by the last firing and keeps putative firing times in an indexed priority queue
+/// - `TauLeap`: explicit tau-leaping with adaptive step selection, every step fires a Poisson distributed batch of reactions
+///              and falls back to exact direct method steps when reactants of critical reactions are near zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimulationAlgorithm {
+    #[default]
+    Direct,
+    NextReaction,
+    TauLeap,
+}
+
+impl std::str::FromStr for SimulationAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_ref() {
+            "direct" => Ok(SimulationAlgorithm::Direct),
+            "next-reaction" | "next_reaction" | "nrm" => Ok(SimulationAlgorithm::NextReaction),
+            "tau-leap" | "tau_leap" | "tau" => Ok(SimulationAlgorithm::TauLeap),
+            _ => Err(format!("Invalid algorithm '{}', expected one of \"direct\", \"next-reaction\", \"tau-leap\"", s)),
+        }
+    }
+}
 
 #[derive(Clone)]
 /// A `ReactionNetwork` represents a computational netowork of chemical reactions.
 ///
-/// It contains four main components:
+/// It contains the following components:
 ///
-/// - `reactions`: a set of all the reactions in the network, represented as instances of `Reaction`.
-/// - `possible_reactions`: a subset of `reactions` that are currently possible to occur based on the current state
+/// - `network`: every reaction in the network compiled over interned species indices, shared by every clone of the network.
+///              Reactions are referred to by their index within this list everywhere else in the network.
+/// - `possible_reactions`: the indices of `reactions` that are currently possible to occur based on the current state
 ///                        of the system (i.e. the concentration of Species in solution). This is updated at each time step.
-/// - `null_adjacent_reactions`: a subset of `reactions` that involve only products, 
+/// - `null_adjacent_reactions`: the indices of `reactions` that involve only products,
 ///                              or involve reactants produced by `reactions` only involving products.
 ///                              i.e. they are adjacent to null species.
 ///                              This is used to speed up computations.
-/// - `solution`: a dictionary that maps Species::Names to their Species::counts
-///
-/// The lifetime parameter `'reaction_network` is used to tie the struct to the lifetime of its dependencies,
-/// such as instances of `Reaction` and `Species`.
-pub struct ReactionNetwork {
-    reactions: HashSet<Reaction>,
-    possible_reactions: HashSet<Reaction>, 
-    null_adjacent_reactions: HashSet<Reaction>,
-    solution: Solution,
+/// - `dependency_graph`: for each reaction, the indices of every reaction whose propensity may change when it fires.
+///                       This is built once from a species -> reaction map when the network is created.
+/// - `release_dependency_graph`: for each reaction, the indices of every reaction whose propensity may change when
+///                               the products of a delayed firing of it are released.
+/// - `propensities`: the propensity of each reaction as of the last time it was evaluated
+/// - `firing_times`: the putative firing time of each reaction, only used by the next reaction method.
+///                   This is generated lazily on the first step so that every cloned trial draws its own times.
+/// - `pending_products`: the products of delayed reactions which have fired but not yet been released, keyed by release time
+/// - `algorithm`: the stochastic simulation algorithm used by `react()`
+/// - `counts`: the current count of each species, in the order of the species of `network`.
+///             Counts are `u64` unless the network was converted to another count type with `with_count_type()`,
+///             every update is checked and a reaction which would overflow a count fails with an error naming the reaction.
+/// - `simulated_time`: the continuous time which has elapsed inside the simulation, advanced by an exponentially
+///                     distributed waiting time each time a reaction fires
+/// - `rng`: the random number generator every random choice in the simulation is drawn from,
+///          seeding it makes the simulation reproducible
+pub struct ReactionNetwork<C: Count = u64> {
+    network: Arc<CompiledNetwork>,
+    possible_reactions: HashSet<usize>,
+    null_adjacent_reactions: HashSet<usize>,
+    dependency_graph: Vec<Vec<usize>>,
+    release_dependency_graph: Vec<Vec<usize>>,
+    propensities: Vec<f64>,
+    firing_times: Option<IndexedPriorityQueue>,
+    pending_products: DelayQueue,
+    algorithm: SimulationAlgorithm,
+    counts: Vec<C>,
+    simulated_time: f64,
+    last_reaction: Option<usize>,
+    rng: StdRng,
 }
 
 impl ReactionNetwork {
 
-    pub fn new(reactions: HashSet<Reaction>, solution: Solution)-> Self {
-        // Initialize null_adjacent_reactions and possible_reactions as empty HashSet
-        let null_adjacent_reactions = HashSet::new();
-        let possible_reactions = HashSet::new();
+    pub fn new(mut reactions: Vec<Reaction>, solution: Solution)-> Self {
+        // Fix an order for the reactions so they may be referred to by index,
+        // sorting makes the order independent of how the reactions were collected so seeded simulations are reproducible.
+        // Repeated reactions are separate channels which fire identically so their relative order does not matter
+        reactions.sort();
+        let propensities = vec![0.0; reactions.len()];
+
+        // Intern species names once so the rest of the simulation only deals in indices
+        let network = CompiledNetwork::from(&reactions);
+        let counts = network.get_counts(&solution);
 
         // Make a new instance of Self with the provided arguments and initialized fields.
-        let mut new_netowrk = Self{reactions, solution, null_adjacent_reactions, possible_reactions};
+        let mut new_netowrk = Self{
+            network: Arc::new(network),
+            possible_reactions: HashSet::new(),
+            null_adjacent_reactions: HashSet::new(),
+            dependency_graph: Vec::new(),
+            release_dependency_graph: Vec::new(),
+            propensities,
+            firing_times: None,
+            pending_products: DelayQueue::new(),
+            algorithm: SimulationAlgorithm::default(),
+            counts,
+            simulated_time: 0.0,
+            last_reaction: None,
+            rng: StdRng::from_entropy(),
+        };
 
-        // Generate and cache null adjacent reactions up front
+        // Generate and cache null adjacent reactions and reaction dependencies up front
         new_netowrk.gen_null_adjacent_reactions();
+        new_netowrk.gen_dependency_graph();
+        new_netowrk.find_possible_reactions();
 
         return new_netowrk;
     }
 
-    pub fn get_null_adjacent_reactions(&self) -> &HashSet<Reaction> {
+    /// maps the current state of the reaction network back to a named solution
+    pub fn get_solution(&self) -> Solution {
+        return self.network.get_solution(&self.counts);
+    }
+}
+
+impl<C: Count> ReactionNetwork<C> {
+
+    /// Converts the network to store its counts as another type,
+    /// e.g. arbitrary precision `BigUint` counts for networks whose counts grow without bound
+    pub fn with_count_type<D: Count>(self) -> ReactionNetwork<D> {
+        let counts = self.counts.iter()
+            .map(|count| D::from_biguint(&count.to_biguint()).expect("count does not fit in the new count type"))
+            .collect();
+
+        return ReactionNetwork{
+            network: self.network,
+            possible_reactions: self.possible_reactions,
+            null_adjacent_reactions: self.null_adjacent_reactions,
+            dependency_graph: self.dependency_graph,
+            release_dependency_graph: self.release_dependency_graph,
+            propensities: self.propensities,
+            firing_times: self.firing_times,
+            pending_products: self.pending_products,
+            algorithm: self.algorithm,
+            counts,
+            simulated_time: self.simulated_time,
+            last_reaction: self.last_reaction,
+            rng: self.rng,
+        };
+    }
+
+    /// Sets the algorithm used by `react()`
+    pub fn set_algorithm(&mut self, algorithm: SimulationAlgorithm) {
+        self.algorithm = algorithm;
+        self.firing_times = None;
+    }
+
+    /// Reseeds the random number generator so the rest of the simulation is reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.firing_times = None;
+    }
+
+    pub fn get_algorithm(&self) -> SimulationAlgorithm {
+        return self.algorithm;
+    }
+
+    pub fn get_null_adjacent_reactions(&self) -> &HashSet<usize> {
         // Returns a reference to the null_adjacent_reactions HashSet
         return &self.null_adjacent_reactions;
     }
@@ -53,22 +183,22 @@ impl ReactionNetwork {
 
         self.null_adjacent_reactions.clear();
 
-        for reaction in &self.reactions {
+        let reactions = self.network.get_reactions();
+        for (index, reaction) in reactions.iter().enumerate() {
             // Check for reactions that only have products (null adjacent).
             if reaction.get_reactants().is_empty() {
 
                 // Insert the reaction into the null_adjacent_reactions HashSet and access its corresponding product(s)
-                if self.null_adjacent_reactions.insert(reaction.clone()) {
-                    for product in reaction.get_products() {
-                        let null_generated_species = product.get_species_name();
+                if self.null_adjacent_reactions.insert(index) {
+                    for (null_generated_species, _) in reaction.get_products() {
 
                         // For each secondary reaction, check if its reactant species matches the current null generated species
-                        for secondary_reaction in &self.reactions {
-                            for secondary_reactant in secondary_reaction.get_reactants() {
+                        for (secondary_index, secondary_reaction) in reactions.iter().enumerate() {
+                            for (secondary_reactant, _) in secondary_reaction.get_reactants() {
 
-                                if null_generated_species == secondary_reactant.get_species_name() {
+                                if null_generated_species == secondary_reactant {
                                     // Insert the reaction into the null_adjacent_reactions HashSet.
-                                    self.null_adjacent_reactions.insert(secondary_reaction.clone());
+                                    self.null_adjacent_reactions.insert(secondary_index);
                                 }
                             }
                         }
@@ -78,82 +208,520 @@ impl ReactionNetwork {
         }
     }
 
+    // Builds the reaction dependency graph from a map of each species to the reactions which consume it.
+    // A reaction depends on another if the other changes the count of one of its reactants,
+    // every reaction also depends on itself so that it is always rescheduled after firing.
+    // Delayed reactions only change their reactants when they fire, and their products when they are released.
+    fn gen_dependency_graph(&mut self) {
+        let reactions = self.network.get_reactions();
+        let mut species_dependents: Vec<Vec<usize>> = vec![Vec::new(); self.network.get_species().len()];
+        for (index, reaction) in reactions.iter().enumerate() {
+            for (species, _) in reaction.get_reactants() {
+                species_dependents[*species].push(index);
+            }
+        }
+
+        let mut dependency_graph = Vec::with_capacity(reactions.len());
+        for (index, reaction) in reactions.iter().enumerate() {
+            let mut dependents = HashSet::from([index]);
+            for (species, _) in reaction.get_initiation_changes() {
+                dependents.extend(&species_dependents[*species]);
+            }
+
+            let mut dependents: Vec<usize> = dependents.into_iter().collect();
+            dependents.sort_unstable();
+            dependency_graph.push(dependents);
+        }
+
+        let mut release_dependency_graph = Vec::with_capacity(reactions.len());
+        for reaction in reactions {
+            let mut dependents = HashSet::new();
+            if reaction.get_delay().is_some() {
+                for (species, _) in reaction.get_products() {
+                    dependents.extend(&species_dependents[*species]);
+                }
+            }
+
+            let mut dependents: Vec<usize> = dependents.into_iter().collect();
+            dependents.sort_unstable();
+            release_dependency_graph.push(dependents);
+        }
+
+        self.dependency_graph = dependency_graph;
+        self.release_dependency_graph = release_dependency_graph;
+    }
+
+    /// returns the compiled network shared by every clone of this network
+    pub fn get_network(&self) -> &Arc<CompiledNetwork> {
+        return &self.network;
+    }
+
+    /// returns every reaction in the network in index order
+    pub fn get_reactions(&self) -> &[CompiledReaction] {
+        return self.network.get_reactions();
+    }
+
+    /// returns the reaction stored at some index
+    pub fn get_reaction(&self, index: usize) -> &CompiledReaction {
+        return self.network.get_reaction(index);
+    }
+
+    /// returns the name of every species in the order used by `get_counts()`
+    pub fn get_species(&self) -> &[String] {
+        return self.network.get_species();
+    }
 
-    pub fn get_possible_reactions(&self) -> &HashSet<Reaction> {
+    pub fn get_possible_reactions(&self) -> &HashSet<usize> {
         return &self.possible_reactions;
     }
 
-    fn find_possible_reactions<'finding>(&'finding mut self) {
+    fn find_possible_reactions(&mut self) {
         self.possible_reactions.clear();
-        
+
         // loop over all reactions and check if it's possible for them to occur based on current species concentration
-        for reaction in &self.reactions {
-            if reaction.is_possible(&self.solution.species_counts) {
-                self.possible_reactions.insert(reaction.clone()); // add reaction to list of possible reactions
-            }
+        for index in 0..self.propensities.len() {
+            self.update_propensity(index);
         }
     }
 
-    fn sum_reaction_rates (&self) -> u128 {
-        let mut sum: u128 = 0; 
-        // loop over all possible reactions and sum their reaction rates
-        for reaction in &self.possible_reactions {
-            sum += reaction.get_reaction_rate();
+    // Recomputes the propensity of a single reaction and updates whether it is possible
+    // a reaction with zero propensity can never fire so it is not considered possible
+    fn update_propensity(&mut self, index: usize) -> f64 {
+        let reaction = self.network.get_reaction(index);
+        let propensity = if reaction.is_possible(&self.counts) {
+            reaction.get_propensity(&self.counts)
+        } else {
+            0.0
+        };
+
+        self.propensities[index] = propensity;
+        if propensity > 0.0 {
+            self.possible_reactions.insert(index);
+        } else {
+            self.possible_reactions.remove(&index);
         }
-        return sum;
+
+        return propensity;
     }
 
+    fn sum_propensities (&self) -> f64 {
+        // sum in index order rather than over the possible reactions set so the result does not depend on hashing,
+        // impossible reactions have a propensity of 0
+        return self.propensities.iter().sum();
+    }
 
-    // Get a possible reaction from the set of possible reactions with weighted probability
-    pub fn get_next_reaction<'getting> (&'getting self) -> Option<Reaction> {
-        let mut index = rand::thread_rng().gen_range(0.. self.sum_reaction_rates());
-        let mut next_reaction: Option<Reaction>= None;
 
-        // iterate through all possible valid reactions and pick one based on its probability 
-        for reaction in self.get_possible_reactions() {
-            if reaction.get_reaction_rate() > index {
-                next_reaction = Some(reaction.clone());
+    // Get a possible reaction from the set of possible reactions weighted by its mass action propensity
+    pub fn get_next_reaction(&mut self) -> Option<usize> {
+        let total_propensity = self.sum_propensities();
+        if total_propensity <= 0.0 {
+            return None;
+        }
+
+        let mut index = self.rng.gen_range(0.0..total_propensity);
+        let mut next_reaction: Option<usize> = None;
+
+        // iterate through all possible valid reactions in index order and pick one based on its probability
+        for (reaction, propensity) in self.propensities.iter().enumerate() {
+            if *propensity <= 0.0 {
+                continue;
+            }
+            next_reaction = Some(reaction);
+            if *propensity > index {
                 break;
             } else {
-                index -= reaction.get_reaction_rate();
+                index -= propensity;
             }
         }
 
+        // floating point rounding can leave a sliver past the final reaction, which then takes it
         return next_reaction;
     }
 
-    // This function reacts based on the randomly selected Reaction instance
-    pub fn react<'reacting> (&'reacting mut self) {
-        // update the list of possible reactions. 
+    /// Fires a single reaction using the selected simulation algorithm
+    /// and advances simulated time to the moment it fired.
+    /// If the products of a delayed reaction are due before the next reaction would fire they are released instead.
+    /// Returns an error naming the reaction if firing it would take a count out of range
+    pub fn react(&mut self) -> Result<(), String> {
+        self.last_reaction = None;
+        return match self.algorithm {
+            SimulationAlgorithm::Direct => self.react_direct(),
+            SimulationAlgorithm::NextReaction => self.react_next_reaction(),
+            SimulationAlgorithm::TauLeap => self.react_tau_leap(),
+        };
+    }
+
+    // Gillespie direct method:
+    // picks a reaction weighted by its propensity and advances simulated time
+    // by an exponentially distributed waiting time with rate equal to the total propensity
+    fn react_direct(&mut self) -> Result<(), String> {
+        // update the list of possible reactions.
         self.find_possible_reactions();
+        let total_propensity = self.sum_propensities();
+        let waiting_time = sample_exponential(total_propensity, &mut self.rng);
 
-        if !self.possible_reactions.is_empty() {
-            if let Some(reaction) = self.get_next_reaction() {
-                for reactant in reaction.get_reactants() {
-                    self.solution.species_counts.entry(reactant.get_species_name().clone())
-                        .and_modify(|species_count|
-                            if let Species::Count(current_count) =species_count{
-                                *current_count -= reactant.get_coefficient();
-                            });
-                }
+        // release delayed products first if they are due before the next firing,
+        // the waiting time is memoryless so it is simply redrawn on the next step
+        let release_time = self.get_next_release_time();
+        if release_time <= self.simulated_time + waiting_time && release_time.is_finite() {
+            self.simulated_time = release_time;
+            self.release_products_until(release_time)?;
+            self.find_possible_reactions();
+            return Ok(());
+        }
+
+        // rounding past the final reaction falls back to the last reaction with a positive propensity,
+        // and no reaction is picked only when none can fire, in which case the network is stable
+        if let Some(reaction) = self.get_next_reaction() {
+            self.simulated_time += waiting_time;
+            self.apply_reaction(reaction)?;
+        }
+        return Ok(());
+    }
+
+    // Gibson and Bruck next reaction method:
+    // fires the reaction with the smallest putative firing time,
+    // then reschedules only the reactions which depend on the species it changed
+    fn react_next_reaction(&mut self) -> Result<(), String> {
+        if self.firing_times.is_none() {
+            self.find_possible_reactions();
+            // firing times are absolute, and are regenerated mid-trial after a change of algorithm or seed
+            let (rng, simulated_time) = (&mut self.rng, self.simulated_time);
+            let times = self.propensities.iter().map(|propensity| simulated_time + sample_exponential(*propensity, rng)).collect();
+            self.firing_times = Some(IndexedPriorityQueue::from(times));
+        }
+
+        let next_firing = self.firing_times.as_ref().and_then(|times| times.peek());
+        let firing_time = next_firing.map(|(_, firing_time)| firing_time).unwrap_or(f64::INFINITY);
 
-                for product in reaction.get_products() {
-                    self.solution.species_counts.entry(product.get_species_name().clone())
-                        .and_modify(|species_count|
-                            if let Species::Count(current_count) = species_count {
-                                *current_count += product.get_coefficient();
-                            });
+        // delayed products due before the next firing are released first,
+        // then reactions consuming them are rescheduled exactly as if a reaction had produced them
+        let release_time = self.get_next_release_time();
+        if release_time <= firing_time && release_time.is_finite() {
+            self.simulated_time = release_time;
+            for released in self.release_products_until(release_time)? {
+                for dependent_index in 0..self.release_dependency_graph[released].len() {
+                    let dependent = self.release_dependency_graph[released][dependent_index];
+                    self.reschedule(dependent, false);
                 }
-            } 
-            else {
-                panic!("failed to get next reaction in react()");
             }
+            return Ok(());
         }
+
+        let reaction = match next_firing {
+            Some((reaction, _)) if firing_time.is_finite() => reaction,
+            _ => return Ok(()),
+        };
+
+        self.simulated_time = firing_time;
+        self.apply_reaction(reaction)?;
+
+        for dependent_index in 0..self.dependency_graph[reaction].len() {
+            let dependent = self.dependency_graph[reaction][dependent_index];
+            self.reschedule(dependent, dependent == reaction);
+        }
+        return Ok(());
     }
 
-    // returns a reference to the map containing the current state of the reaction network 
-    pub fn get_solution(&self) -> &Solution {
-        return &self.solution;
+    // Recomputes the propensity of a reaction after the solution changed and updates its putative firing time
+    // for the next reaction method
+    fn reschedule(&mut self, dependent: usize, fired: bool) {
+        let old_propensity = self.propensities[dependent];
+        let new_propensity = self.update_propensity(dependent);
+        let firing_times = self.firing_times.as_mut().expect("firing times are generated before reacting");
+
+        let new_time = if fired || old_propensity <= 0.0 {
+            // the fired reaction, or one that was previously disabled, draws a fresh waiting time
+            self.simulated_time + sample_exponential(new_propensity, &mut self.rng)
+        } else if new_propensity > 0.0 {
+            // otherwise rescale the remaining waiting time by the change in propensity
+            self.simulated_time + (old_propensity / new_propensity) * (firing_times.get_time(dependent) - self.simulated_time)
+        } else {
+            f64::INFINITY
+        };
+        firing_times.update(dependent, new_time);
+    }
+
+    // Applies the stoichiometry of the reaction at some index to the solution
+    // a delayed reaction consumes its reactants now and schedules its products to be released after its delay
+    fn apply_reaction(&mut self, index: usize) -> Result<(), String> {
+        self.last_reaction = Some(index);
+        let reaction = self.network.get_reaction(index);
+
+        // every reactant is checked before any count changes so a failed reaction leaves the solution untouched
+        if !reaction.is_possible(&self.counts) {
+            let (species, _) = reaction.get_reactants().iter()
+                .find(|(species, coefficient)| self.counts[*species].saturating_to_u64() < *coefficient)
+                .copied()
+                .unwrap_or_default();
+            return Err(self.count_error(species, index, "would become negative"));
+        }
+        for (species, coefficient) in reaction.get_reactants() {
+            self.counts[*species].try_sub(*coefficient);
+        }
+
+        if reaction.get_delay().is_some() {
+            self.schedule_products(index);
+            return Ok(());
+        }
+        return self.add_products(index);
+    }
+
+    // Schedules the products of a delayed reaction which fired at the current simulated time
+    fn schedule_products(&mut self, index: usize) {
+        if let Some(delay) = self.network.get_reaction(index).get_delay() {
+            let release_time = self.simulated_time + delay.sample(&mut self.rng);
+            self.pending_products.push(release_time, index);
+        }
+    }
+
+    // Adds the products of the reaction at some index to the solution
+    fn add_products(&mut self, index: usize) -> Result<(), String> {
+        for (species, coefficient) in self.network.get_reaction(index).get_products() {
+            if !self.counts[*species].try_add(*coefficient) {
+                return Err(self.count_error(*species, index, "overflowed"));
+            }
+        }
+        return Ok(());
+    }
+
+    // Releases every pending product due at or before some time,
+    // returns the index of the reaction each released batch of products came from
+    fn release_products_until(&mut self, time: f64) -> Result<Vec<usize>, String> {
+        let mut released = Vec::new();
+        while self.pending_products.peek_time().is_some_and(|release_time| release_time <= time) {
+            let (_, index) = self.pending_products.pop().expect("a release time was peeked");
+            self.add_products(index)?;
+            released.push(index);
+        }
+        return Ok(released);
+    }
+
+    // returns the earliest time pending products will be released, infinity if nothing is pending
+    fn get_next_release_time(&self) -> f64 {
+        return self.pending_products.peek_time().unwrap_or(f64::INFINITY);
+    }
+
+    /// returns true while delayed reactions have fired whose products have not yet been released
+    pub fn has_pending_products(&self) -> bool {
+        return !self.pending_products.is_empty();
+    }
+
+    // returns the current count of the species at some index, counts too large for a u64 are clamped
+    fn get_count(&self, species: usize) -> u64 {
+        return self.counts[species].saturating_to_u64();
+    }
+
+    // returns the simulated time which has elapsed since the network was created
+    pub fn get_simulated_time(&self) -> f64 {
+        return self.simulated_time;
+    }
+
+    /// returns the index of the single reaction fired by the last call to `react()`,
+    /// None if it released delayed products, leapt over several reactions, or nothing could fire
+    pub fn get_last_reaction(&self) -> Option<usize> {
+        return self.last_reaction;
+    }
+
+    /// returns the reaction at some index by its label, or written with species names if it has none
+    pub fn describe_reaction(&self, index: usize) -> String {
+        return self.network.describe_reaction(index);
+    }
+
+    /// returns the current count of every species, in the order of `get_species()`
+    pub fn get_counts(&self) -> &[C] {
+        return &self.counts;
+    }
+
+    // describes a count which a reaction would take out of range
+    fn count_error(&self, species: usize, reaction: usize, problem: &str) -> String {
+        return format!("the count of {} {} when reaction '{}' fired", self.get_species()[species], problem, self.network.describe_reaction(reaction));
     }
 }
 
+/// Draws an exponentially distributed waiting time with the given rate,
+/// returns infinity if the rate is zero since such an event never happens
+fn sample_exponential<R: Rng>(rate: f64, rng: &mut R) -> f64 {
+    if rate <= 0.0 {
+        return f64::INFINITY;
+    }
+
+    // draw from (0, 1] so that the logarithm is always finite
+    let uniform: f64 = 1.0 - rng.gen::<f64>();
+    return -uniform.ln() / rate;
+}
+
+/// Parses the reactions written in the text of an input file, for tests, panicking if the text has any errors
+#[cfg(test)]
+pub(crate) fn parse_test_reactions(input: &str) -> Vec<Reaction> {
+    let (reactions, diagnostics) = crate::parser::parse_reactions("input.csv", input);
+    let errors: Vec<String> = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).map(|diagnostic| diagnostic.to_string()).collect();
+    assert!(errors.is_empty(), "test reactions have errors:\n{}", errors.join("\n"));
+    return reactions.into_iter().map(|(_, reaction)| reaction).collect();
+}
+
+/// Builds a network from the text of an input file and an init file, for tests, panicking if either text has any errors.
+/// Species which the init file does not set start at 0
+#[cfg(test)]
+pub(crate) fn parse_test_network(input: &str, init: &str) -> ReactionNetwork {
+    use std::collections::{BTreeMap, HashMap};
+    use reaction::term::solution::Species;
+
+    let reactions = parse_test_reactions(input);
+    let mut initial_counts: BTreeMap<String, u64> = reactions.iter()
+        .flat_map(|reaction| reaction.get_reactants().iter().chain(reaction.get_products()))
+        .map(|term| (term.get_species_name().to_string(), 0))
+        .collect();
+    let (_, diagnostics) = crate::parser::parse_init("init.csv", init, &mut initial_counts);
+    let errors: Vec<String> = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).map(|diagnostic| diagnostic.to_string()).collect();
+    assert!(errors.is_empty(), "test initial counts have errors:\n{}", errors.join("\n"));
+
+    let species_counts: HashMap<Species, Species> = initial_counts.into_iter()
+        .map(|(name, count)| (Species::Name(name), Species::Count(count)))
+        .collect();
+    return ReactionNetwork::new(reactions, Solution{species_counts});
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_network() -> ReactionNetwork {
+        return parse_test_network("A + B => C, 2", "A, 5\nB, 3");
+    }
+
+    fn run_to_completion(network: &mut ReactionNetwork) {
+        while !network.get_possible_reactions().is_empty() {
+            network.react().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_direct_method() {
+        let mut network = test_network();
+        run_to_completion(&mut network);
+
+        // species are ordered A, B, C
+        assert_eq!(network.get_counts(), &[2, 0, 3]);
+        assert!(network.get_simulated_time() > 0.0);
+    }
+
+    #[test]
+    fn test_next_reaction_method() {
+        let mut network = test_network();
+        network.set_algorithm(SimulationAlgorithm::NextReaction);
+        run_to_completion(&mut network);
+
+        // species are ordered A, B, C
+        assert_eq!(network.get_counts(), &[2, 0, 3]);
+        assert!(network.get_simulated_time() > 0.0);
+    }
+
+    #[test]
+    fn test_switching_algorithm_mid_run() {
+        // switching algorithm and seed before every firing
+        let mut network = parse_test_network("A => B, 1", "A, 100");
+
+        let mut previous_time = 0.0;
+        for step in 0..100 {
+            let algorithm = if step % 2 == 0 { SimulationAlgorithm::NextReaction } else { SimulationAlgorithm::Direct };
+            network.set_algorithm(algorithm);
+            network.set_seed(step);
+            network.react().unwrap();
+
+            // time never runs backwards when firing times are regenerated
+            assert!(network.get_simulated_time() > previous_time, "time went from {} to {} at step {}", previous_time, network.get_simulated_time(), step);
+            previous_time = network.get_simulated_time();
+        }
+        assert_eq!(network.get_counts(), &[0, 100]);
+    }
+
+    #[test]
+    fn test_delayed_products() {
+        for algorithm in [SimulationAlgorithm::Direct, SimulationAlgorithm::NextReaction, SimulationAlgorithm::TauLeap] {
+            let mut network = parse_test_network("A => B, 1, fixed(5)", "A, 3");
+            network.set_algorithm(algorithm);
+
+            // the first firing consumes A but B is only released after the delay
+            network.react().unwrap();
+            assert_eq!(network.get_counts(), &[2, 0]);
+            assert!(network.has_pending_products());
+
+            while !network.get_possible_reactions().is_empty() || network.has_pending_products() {
+                network.react().unwrap();
+            }
+            assert_eq!(network.get_counts(), &[0, 3]);
+            assert!(network.get_simulated_time() >= 5.0);
+        }
+    }
+
+    #[test]
+    fn test_count_overflow() {
+        // starting at the largest u64 count
+        let network = parse_test_network("A => 2 A, 1", &format!("A, {}", u64::MAX));
+
+        let mut checked = network.clone();
+        assert_eq!(checked.react(), Err(String::from("the count of A overflowed when reaction 'A => 2 A' fired")));
+
+        let mut big = network.with_count_type::<num_bigint::BigUint>();
+        big.react().unwrap();
+        assert_eq!(big.get_counts(), &[num_bigint::BigUint::from(u64::MAX) + 1_u32]);
+    }
+
+    #[test]
+    fn test_failed_reaction_leaves_counts_untouched() {
+        let mut network = test_network();
+        run_to_completion(&mut network);
+
+        // B is exhausted, A must not be consumed when the reaction is forced
+        assert!(network.apply_reaction(0).is_err());
+        assert_eq!(network.get_counts(), &[2, 0, 3]);
+    }
+
+    #[test]
+    fn test_zero_and_leak_rates() {
+        // A => B, 0 can never fire while the leak A => C, 1e-9 eventually does
+        for algorithm in [SimulationAlgorithm::Direct, SimulationAlgorithm::NextReaction, SimulationAlgorithm::TauLeap] {
+            let mut network = parse_test_network("A => B, 0\nA => C, 1e-9", "A, 1");
+            network.set_algorithm(algorithm);
+            network.set_seed(7);
+            assert_eq!(network.get_possible_reactions().len(), 1);
+
+            run_to_completion(&mut network);
+            assert_eq!(network.get_counts(), &[0, 0, 1]);
+            assert!(network.get_simulated_time() > 0.0);
+        }
+
+        // a network whose total rate is 0 is stable from the start
+        let mut network = parse_test_network("A => B, 0", "A, 1");
+        assert!(network.get_possible_reactions().is_empty());
+        network.react().unwrap();
+        assert_eq!(network.get_counts(), &[1, 0]);
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let network = test_network();
+        assert_eq!(network.dependency_graph, vec![vec![0]]);
+    }
+
+    #[test]
+    fn test_seeded_networks_are_reproducible() {
+        // A + B => C and A => D compete for A
+        let seeded_network = |algorithm: SimulationAlgorithm| {
+            // each network hashes its own sets differently so reaction order must not depend on hashing
+            let mut network = parse_test_network("A + B => C, 2\nA => D, 1", "A, 50\nB, 30");
+            network.set_algorithm(algorithm);
+            network.set_seed(42);
+            run_to_completion(&mut network);
+            return network;
+        };
+
+        for algorithm in [SimulationAlgorithm::Direct, SimulationAlgorithm::NextReaction, SimulationAlgorithm::TauLeap] {
+            let first = seeded_network(algorithm);
+            let second = seeded_network(algorithm);
+            assert_eq!(first.get_counts(), second.get_counts());
+            assert_eq!(first.get_simulated_time().to_bits(), second.get_simulated_time().to_bits());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::hash::{Hash, Hasher};
+use rand::Rng;
+use rand_distr::{Distribution, Exp, Gamma};
+
+/// The time between a delayed reaction consuming its reactants and releasing its products.
+/// Delays are written in the optional third field of a reaction
+/// - `Fixed`: a constant duration, written as a plain number `5` or `fixed(5)`
+/// - `Exponential`: an exponentially distributed duration with the given mean, written `exp(5)`
+/// - `Uniform`: a duration drawn uniformly between a minimum and maximum, written `uniform(1, 3)`
+/// - `Gamma`: a gamma distributed duration with the given shape and scale, written `gamma(4, 0.5)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delay {
+    Fixed(f64),
+    Exponential(f64),
+    Uniform(f64, f64),
+    Gamma(f64, f64),
+}
+
+impl Delay {
+
+    /// parses a delay from a string slice
+    /// panics if the delay is malformed or could be negative
+    pub fn from(delay: &str) -> Self {
+        return Self::parse(delay).unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    /// parses a delay from a string slice
+    /// returns an error if the delay is malformed or could be negative
+    pub fn parse(delay: &str) -> Result<Self, String> {
+        let delay = delay.trim();
+        let invalid = || format!("Invalid reaction delay '{}' - expected a duration such as '5', 'fixed(5)', 'exp(5)', 'uniform(1, 3)', or 'gamma(4, 0.5)'", delay);
+
+        // a plain number is a fixed delay
+        if let Ok(duration) = delay.parse::<f64>() {
+            return Self::checked(Self::Fixed(duration)).ok_or_else(invalid);
+        }
+
+        let (name, arguments) = delay.strip_suffix(')').and_then(|delay| delay.split_once('(')).ok_or_else(invalid)?;
+        let arguments: Vec<f64> = arguments.split(',')
+            .map(|argument| argument.trim().parse::<f64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<f64>, String>>()?;
+
+        let parsed = match (name.trim().to_lowercase().as_ref(), arguments.as_slice()) {
+            ("fixed", [duration]) => Self::Fixed(*duration),
+            ("exp" | "exponential", [mean]) => Self::Exponential(*mean),
+            ("uniform", [min, max]) => Self::Uniform(*min, *max),
+            ("gamma", [shape, scale]) => Self::Gamma(*shape, *scale),
+            _ => return Err(invalid()),
+        };
+        return Self::checked(parsed).ok_or_else(invalid);
+    }
+
+    // returns the delay if every parameter describes a finite, non negative duration
+    fn checked(delay: Self) -> Option<Self> {
+        let valid = |value: f64| value.is_finite() && value >= 0.0;
+        let is_valid = match delay {
+            Self::Fixed(duration) => valid(duration),
+            Self::Exponential(mean) => valid(mean),
+            Self::Uniform(min, max) => valid(min) && valid(max) && min <= max,
+            Self::Gamma(shape, scale) => valid(shape) && valid(scale) && shape > 0.0,
+        };
+        return if is_valid { Some(delay) } else { None };
+    }
+
+    /// draws a single duration from this delay
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        return match *self {
+            Self::Fixed(duration) => duration,
+            Self::Exponential(mean) if mean > 0.0 => Exp::new(1.0 / mean).expect("mean is positive and finite").sample(rng),
+            Self::Exponential(_) => 0.0,
+            Self::Uniform(min, max) if min < max => rng.gen_range(min..max),
+            Self::Uniform(min, _) => min,
+            Self::Gamma(_, scale) if scale <= 0.0 => 0.0,
+            Self::Gamma(shape, scale) => Gamma::new(shape, scale).expect("shape and scale are positive and finite").sample(rng),
+        };
+    }
+}
+
+// delays are always finite so they may be compared and hashed by their bits
+impl Eq for Delay {}
+
+impl Hash for Delay {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match *self {
+            Self::Fixed(value) | Self::Exponential(value) => value.to_bits().hash(state),
+            Self::Uniform(first, second) | Self::Gamma(first, second) => {
+                first.to_bits().hash(state);
+                second.to_bits().hash(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delay() {
+        assert_eq!(Delay::from(" 2.5 "), Delay::Fixed(2.5));
+        assert_eq!(Delay::from("fixed(3)"), Delay::Fixed(3.0));
+        assert_eq!(Delay::from("exp(2)"), Delay::Exponential(2.0));
+        assert_eq!(Delay::from("uniform(1, 3)"), Delay::Uniform(1.0, 3.0));
+        assert_eq!(Delay::from("gamma(4, 0.5)"), Delay::Gamma(4.0, 0.5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_delay() {
+        Delay::from("uniform(3, 1)");
+    }
+
+    #[test]
+    fn test_sample_delay() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(Delay::Fixed(2.0).sample(&mut rng), 2.0);
+        for _ in 0..100 {
+            let duration = Delay::Uniform(1.0, 3.0).sample(&mut rng);
+            assert!((1.0..3.0).contains(&duration));
+        }
+    }
+}
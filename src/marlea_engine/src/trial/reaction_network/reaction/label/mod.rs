@@ -0,0 +1,69 @@
+use std::fmt::{self, Display};
+
+/// Which half of a reversible reaction, written with `<=>` or `<->`, a reaction is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    Forward,
+    Reverse,
+}
+
+impl Direction {
+
+    /// returns the direction of the other half of the same reversible reaction
+    pub fn opposite(&self) -> Self {
+        return match self {
+            Self::Forward => Self::Reverse,
+            Self::Reverse => Self::Forward,
+        };
+    }
+}
+
+/// The label of a reaction, shared by both halves of a reversible reaction so they may be shown as one pair
+/// - `name`: the name of the reaction, for a reversible reaction this defaults to the reaction written with `<=>`
+/// - `direction`: which half of a reversible reaction this is, None for a reaction which is not reversible
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Label {
+    name: String,
+    direction: Option<Direction>,
+}
+
+impl Label {
+
+    pub fn new(name: String) -> Self {
+        return Self{name, direction: None};
+    }
+
+    /// creates the label of one half of a reversible reaction
+    pub fn reversible(name: String, direction: Direction) -> Self {
+        return Self{name, direction: Some(direction)};
+    }
+
+    /// returns the name shared by every reaction with this label
+    pub fn get_name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// returns which half of a reversible reaction this is, None for a reaction which is not reversible
+    pub fn get_direction(&self) -> Option<Direction> {
+        return self.direction;
+    }
+
+    /// returns true if both labels belong to opposite halves of the same reversible reaction
+    pub fn is_reverse_of(&self, other: &Self) -> bool {
+        return match (self.direction, other.direction) {
+            (Some(direction), Some(other_direction)) => self.name == other.name && direction.opposite() == other_direction,
+            _ => false,
+        };
+    }
+}
+
+// writes the name, followed by the direction for half of a reversible reaction, e.g. `A + B <=> C, forward`
+impl Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self.direction {
+            Some(Direction::Forward) => write!(f, "{}, forward", self.name),
+            Some(Direction::Reverse) => write!(f, "{}, reverse", self.name),
+            None => write!(f, "{}", self.name),
+        };
+    }
+}
@@ -1,23 +1,82 @@
 pub mod term;
+pub mod delay;
+pub mod label;
 
-use std::{collections::{HashSet, HashMap}, hash::{Hash, Hasher}};
+use std::{cmp::Ordering, collections::{HashSet, HashMap}, fmt::{self, Display}, hash::{Hash, Hasher}};
 use std::collections::hash_map::DefaultHasher;
 use term::{Term, solution::Species};
+use delay::Delay;
+use label::{Label, Direction};
+
+// the kind of a delay followed by the bits of its parameters
+type DelayKey = (u8, u64, u64);
 
 /// Stores a set for the reaction reactants and products. 
 /// Eeach element contains the variable key used by a Solution struct as well as a reaction rate. 
+/// Rates are non negative floats, so very slow reactions such as leaks may be given rates like `1e-9`.
+/// A delayed reaction consumes its reactants when it fires but only releases its products once its delay has passed.
+/// A reaction may carry a label, which both halves of a reversible reaction share.
 /// This struct should only be used inside of the Reaction_Network Struct 
-#[derive(Debug, Eq, PartialEq,Clone)]
+#[derive(Debug, PartialEq,Clone)]
 pub struct Reaction {
     reactants: HashSet<Term>,
     products: HashSet<Term>,
-    reaction_rate: u64,
+    reaction_rate: f64,
+    delay: Option<Delay>,
+    label: Option<Label>,
 }
 
 impl Reaction {
 
-    pub fn new (reactants: HashSet<Term>, products: HashSet<Term>, reaction_rate: u64) -> Self {
-        return Self { reactants: reactants, products: products, reaction_rate: reaction_rate};
+    /// creates an immediate reaction, terms naming the same species on one side are merged by summing their coefficients
+    /// panics if the reaction rate is negative, infinite, or NaN
+    pub fn new (reactants: HashSet<Term>, products: HashSet<Term>, reaction_rate: f64) -> Self {
+        let mut reaction = Self { reactants: Term::merge(reactants), products: Term::merge(products), reaction_rate: 0.0, delay: None, label: None };
+        reaction.set_reaction_rate(reaction_rate);
+        return reaction;
+    }
+
+    /// creates both halves of a reversible reaction, `reactants <=> products`, as a forward and a reverse reaction
+    /// which share a label named after the reversible reaction
+    /// panics if either reaction rate is negative, infinite, or NaN
+    pub fn reversible (reactants: HashSet<Term>, products: HashSet<Term>, forward_rate: f64, reverse_rate: f64) -> (Self, Self) {
+        let mut forward = Self::new(reactants.clone(), products.clone(), forward_rate);
+        let mut reverse = Self::new(products, reactants, reverse_rate);
+
+        let name = forward.to_string().replacen("=>", "<=>", 1);
+        forward.set_label(Some(Label::reversible(name.clone(), Direction::Forward)));
+        reverse.set_label(Some(Label::reversible(name, Direction::Reverse)));
+        return (forward, reverse);
+    }
+
+    /// sets the reaction rate
+    /// panics if the reaction rate is negative, infinite, or NaN
+    pub fn set_reaction_rate(&mut self, reaction_rate: f64) {
+        if !reaction_rate.is_finite() || reaction_rate < 0.0 {
+            panic!("Invalid reaction rate '{}' - rates must be finite and non negative", reaction_rate);
+        }
+        // -0 is stored as 0 so that equal reactions hash equally
+        self.reaction_rate = if reaction_rate == 0.0 { 0.0 } else { reaction_rate };
+    }
+
+    /// sets the delay between this reaction consuming its reactants and releasing its products
+    pub fn set_delay(&mut self, delay: Option<Delay>) {
+        self.delay = delay;
+    }
+
+    /// sets the label of this reaction
+    pub fn set_label(&mut self, label: Option<Label>) {
+        self.label = label;
+    }
+
+    /// returns the label of this reaction, None if it has none
+    pub fn get_label(&self) -> Option<&Label> {
+        return self.label.as_ref();
+    }
+
+    /// returns the delay of this reaction, None if its products are released as soon as it fires
+    pub fn get_delay(&self) -> Option<&Delay> {
+        return self.delay.as_ref();
     }
     
     /// returns a reference to the reactants set within a reaction
@@ -31,15 +90,46 @@ impl Reaction {
     }
 
     /// returns the reaction rate
-    pub fn get_reaction_rate (&self) -> u128 {
-        return self.reaction_rate as u128;
+    pub fn get_reaction_rate (&self) -> f64 {
+        return self.reaction_rate;
+    }
+
+    /// returns the net change in count of every species altered by the reaction
+    /// species which appear as both reactant and product with the same coefficient (catalysts) are left out,
+    /// so `A + B => A + C` only changes B and C
+    pub fn get_net_changes (&self) -> HashMap<&Species, i64> {
+        let mut net_changes = HashMap::new();
+
+        for reactant in &self.reactants {
+            *net_changes.entry(reactant.get_species_name()).or_insert(0) -= reactant.get_coefficient() as i64;
+        }
+        for product in &self.products {
+            *net_changes.entry(product.get_species_name()).or_insert(0) += product.get_coefficient() as i64;
+        }
+        net_changes.retain(|_, net_change| *net_change != 0);
+
+        return net_changes;
+    }
+
+    /// returns the change in count of every species at the moment the reaction fires
+    /// this is the net change for an immediate reaction, but a delayed reaction only consumes its reactants until its products are released
+    pub fn get_initiation_changes (&self) -> HashMap<&Species, i64> {
+        if self.delay.is_none() {
+            return self.get_net_changes();
+        }
+
+        let mut initiation_changes = HashMap::new();
+        for reactant in &self.reactants {
+            *initiation_changes.entry(reactant.get_species_name()).or_insert(0) -= reactant.get_coefficient() as i64;
+        }
+        return initiation_changes;
     }
 
     pub fn is_possible (&self, solution: &HashMap<Species,Species>) -> bool {
         let mut reaction_possible = true;
 
         for reactant in &self.reactants {
-            if let Some(Species::Count(current_count)) = solution.get(&reactant.get_species_name()) {
+            if let Some(Species::Count(current_count)) = solution.get(reactant.get_species_name()) {
                 if reactant.get_coefficient() > *current_count {
                     reaction_possible = false;
                     break;
@@ -49,6 +139,89 @@ impl Reaction {
         
         return reaction_possible;
     }
+
+    /// Returns the mass action propensity of this reaction for the given solution
+    /// This is `rate * C(count, coefficient)` multiplied over all reactants, i.e. the rate constant times the number of 
+    /// distinct combinations of reactant molecules which could collide to produce this reaction
+    pub fn get_propensity (&self, solution: &HashMap<Species,Species>) -> f64 {
+        let mut propensity = self.reaction_rate;
+
+        // multiply in a fixed order so the rounding of the result does not depend on how the reactants were hashed
+        let mut reactants: Vec<&Term> = self.reactants.iter().collect();
+        reactants.sort();
+        for reactant in reactants {
+            let current_count = match solution.get(reactant.get_species_name()) {
+                Some(Species::Count(current_count)) => *current_count,
+                _ => 0,
+            };
+            propensity *= binomial(current_count, reactant.get_coefficient());
+        }
+
+        return propensity;
+    }
+
+    // returns the reactants, products, rate, delay, and label of this reaction in a form which is ordered independently of hashing
+    fn ordering_key(&self) -> (Vec<&Term>, Vec<&Term>, u64, Option<DelayKey>, Option<&Label>) {
+        let mut reactants: Vec<&Term> = self.reactants.iter().collect();
+        let mut products: Vec<&Term> = self.products.iter().collect();
+        reactants.sort();
+        products.sort();
+
+        let delay = self.delay.map(|delay| match delay {
+            Delay::Fixed(duration) => (0, duration.to_bits(), 0),
+            Delay::Exponential(mean) => (1, mean.to_bits(), 0),
+            Delay::Uniform(min, max) => (2, min.to_bits(), max.to_bits()),
+            Delay::Gamma(shape, scale) => (3, shape.to_bits(), scale.to_bits()),
+        });
+        return (reactants, products, self.reaction_rate.to_bits(), delay, self.label.as_ref());
+    }
+}
+
+// rates are always finite so reactions may be compared and hashed by the bits of their rate
+impl Eq for Reaction {}
+
+// reactions are ordered so that a network assigns the same index to each reaction however its reactions were collected
+impl Ord for Reaction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return self.ordering_key().cmp(&other.ordering_key());
+    }
+}
+
+impl PartialOrd for Reaction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+// writes the reaction without its rate, e.g. `2 A + B => C`
+impl Display for Reaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let side = |terms: &HashSet<Term>| {
+            let mut terms: Vec<&Term> = terms.iter().collect();
+            terms.sort();
+            return terms.iter()
+                .map(|term| match term.get_coefficient() {
+                    1 => term.get_species_name().to_string(),
+                    coefficient => format!("{} {}", coefficient, term.get_species_name()),
+                })
+                .collect::<Vec<String>>()
+                .join(" + ");
+        };
+        return write!(f, "{}", format!("{} => {}", side(&self.reactants), side(&self.products)).trim());
+    }
+}
+
+/// Computes `n choose k` as a float, returns 0 if k > n
+pub(crate) fn binomial (n: u64, k: u64) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+
+    let mut combinations = 1.0;
+    for i in 0..k {
+        combinations *= (n - i) as f64 / (i + 1) as f64;
+    }
+    return combinations;
 }
 
 impl Hash for Reaction {
@@ -60,7 +233,62 @@ impl Hash for Reaction {
         for term in &self.products {
             term.hash(&mut hasher);
         }
-        self.reaction_rate.hash(&mut hasher);
+        self.reaction_rate.to_bits().hash(&mut hasher);
+        self.delay.hash(&mut hasher);
+        self.label.hash(&mut hasher);
         hasher.finish().hash(state);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_propensity() {
+        // 2 A + B => C, 3
+        let reactants = HashSet::from([Term::new(String::from("A"), 2), Term::new(String::from("B"), 1)]);
+        let products = HashSet::from([Term::new(String::from("C"), 1)]);
+        let reaction = Reaction::new(reactants, products, 3.0);
+
+        let solution = HashMap::from([
+            (Species::Name(String::from("A")), Species::Count(4)),
+            (Species::Name(String::from("B")), Species::Count(5)),
+            (Species::Name(String::from("C")), Species::Count(0)),
+        ]);
+
+        // 3 * C(4, 2) * C(5, 1) = 3 * 6 * 5
+        assert_eq!(reaction.get_propensity(&solution), 90.0);
+    }
+
+    #[test]
+    fn test_get_propensity_insufficient_reactants() {
+        let reactants = HashSet::from([Term::new(String::from("A"), 2)]);
+        let reaction = Reaction::new(reactants, HashSet::new(), 3.0);
+
+        let solution = HashMap::from([(Species::Name(String::from("A")), Species::Count(1))]);
+
+        assert_eq!(reaction.get_propensity(&solution), 0.0);
+    }
+
+    #[test]
+    fn test_repeated_and_catalyst_terms() {
+        // A + 2 A + B => A + C, 1 is read as 3 A + B => A + C
+        let reactants = HashSet::from([Term::new(String::from("A"), 1), Term::new(String::from("A"), 2), Term::new(String::from("B"), 1)]);
+        let products = HashSet::from([Term::new(String::from("A"), 1), Term::new(String::from("C"), 1)]);
+        let reaction = Reaction::new(reactants, products, 1.0);
+        assert_eq!(reaction.get_reactants(), &HashSet::from([Term::new(String::from("A"), 3), Term::new(String::from("B"), 1)]));
+
+        let net_changes = reaction.get_net_changes();
+        assert_eq!(net_changes, HashMap::from([
+            (&Species::Name(String::from("A")), -2),
+            (&Species::Name(String::from("B")), -1),
+            (&Species::Name(String::from("C")), 1),
+        ]));
+
+        // A + B => A + C, 1 leaves the catalyst A unchanged
+        let reactants = HashSet::from([Term::new(String::from("A"), 1), Term::new(String::from("B"), 1)]);
+        let products = HashSet::from([Term::new(String::from("A"), 1), Term::new(String::from("C"), 1)]);
+        let reaction = Reaction::new(reactants, products, 1.0);
+        assert!(!reaction.get_net_changes().contains_key(&Species::Name(String::from("A"))));
+    }
+}
@@ -1,61 +1,60 @@
 pub mod solution;
 
+use std::collections::{BTreeMap, HashSet};
 use solution::Species;
+use crate::parser::parse_term;
 
 /// Contains the data for a single term within a larger reaction.
 /// Species is a reference to a named value in solution which will be added to or subtracted from. 
-/// Coefficient is the value to add or subtract
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+/// Coefficient is the value to add or subtract, at most `u32::MAX`
+#[derive(Debug, Hash, Eq, PartialEq, PartialOrd, Ord, Clone)]
 pub struct  Term {
     species_name: Species,
-    coefficient: u8,
+    coefficient: u32,
 }
 
 impl Term {
 
-    /// creates a new term from a string slice
-    /// returns none if the term sould be null
-    pub fn from(term: &str) -> Option<Self> {
-        let mut species_name = None;
-        let mut coefficient = None;
-        let parts: Vec<&str> = term.split(" ").filter(|possible_part| !possible_part.is_empty()).collect();
-
-        for part in parts {
-
-            // if there is no coefficent try to parse as coefficent else parse as a name 
-            let possible_coefficient = part.trim().parse::<u8>();
-            let possible_name = part.trim().to_string();
-
-            match possible_coefficient {
-                Ok(value) => {
-                    if let None = coefficient {coefficient = Some(value)}
-                    else {panic!("more than one numeric value provided: it is unclear which is desired coefficient")}
-                }
-                Err(_) => {
-                    if let None = species_name {species_name = Some(possible_name)}
-                    else {
-                        // non catastrophic error warn user with a print to console. 
-                        print!("more than one possible name found in Term {}.\n{} was used as parsed name and coefficient was assumed to be 1.", term, species_name.clone().unwrap());
-                    }
-                }
-            }
+    /// creates a new term from a string slice such as `2 water`
+    /// returns none if the term sould be null, and an error describing every problem if it is not
+    /// an optional whole number coefficient followed by a species name
+    pub fn from(term: &str) -> Result<Option<Self>, String> {
+        return parse_term(term).map_err(|diagnostics| {
+            diagnostics.iter()
+                .map(|diagnostic| match diagnostic.get_hint() {
+                    Some(hint) => format!("{} in term '{}', {}", diagnostic.get_message(), term.trim(), hint),
+                    None => format!("{} in term '{}'", diagnostic.get_message(), term.trim()),
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        });
+    }
 
-        }
+    pub fn new(name: String, coefficient: u32) -> Self {
+        return Term{species_name:Species::Name(name), coefficient};
+    }
 
-        if let Some(parsed_name) = species_name {
-            match coefficient {
-                Some(parsed_value) => {
-                    return Some(Term::new(parsed_name, parsed_value));
-                },
-                None => {
-                    return Some(Term::new(parsed_name, 1 as u8));
-                }
-            }
-        } else {return None}
+    /// merges terms naming the same species into a single term whose coefficient is their sum,
+    /// so that a side written `A + A` or `A + 2 A` is read as a multiset
+    /// panics if a merged coefficient is larger than `u32::MAX`
+    pub fn merge<I: IntoIterator<Item = Term>>(terms: I) -> HashSet<Term> {
+        return Self::try_merge(terms).unwrap_or_else(|error| panic!("{}", error));
     }
 
-    pub fn new(name: String, coefficient: u8) -> Self {
-        return Term{species_name:Species::Name(name), coefficient};
+    /// merges terms naming the same species as `merge()` does,
+    /// returns an error if a merged coefficient is larger than `u32::MAX`
+    pub fn try_merge<I: IntoIterator<Item = Term>>(terms: I) -> Result<HashSet<Term>, String> {
+        let mut coefficients: BTreeMap<Species, u32> = BTreeMap::new();
+        for term in terms {
+            let coefficient = coefficients.entry(term.species_name.clone()).or_insert(0);
+            *coefficient = coefficient.checked_add(term.coefficient).ok_or_else(|| {
+                format!("the merged coefficient of {} is too large, coefficients may be at most {}", term.species_name, u32::MAX)
+            })?;
+        }
+
+        return Ok(coefficients.into_iter()
+            .map(|(species_name, coefficient)| Term{species_name, coefficient})
+            .collect());
     }
     /// returns the coefficient value of a Term
     pub fn get_coefficient (&self) -> u64 {
@@ -98,22 +97,32 @@ fn test_from() {
     let term_1 = "2 water";
     let term_2 = " NaOH";
     let term_3 = "5 O2";
-    let term_4 = "2water NaCl"; // minorly invalid input 
+    let term_4 = "2water NaCl"; // a coefficient against its name and a second name are both errors
     let term_5 = "";// null input
 
     let expected_1 = Some(Term::new(String::from("water"), 2));
     let expected_2 = Some(Term::new(String::from("NaOH"), 1)); //default coefficient should be 1 if not specified
     let expected_3 = Some(Term::new(String::from("O2"), 5));
-    let expected_4 = Some(Term::new(String::from("2water"), 1));
     let expected_5 = None;
 
-    assert_eq!(Term::from(&term_1), expected_1);
-    assert_eq!(Term::from(&term_2), expected_2);
-    assert_eq!(Term::from(&term_3), expected_3);
-    assert_eq!(Term::from(&term_4), expected_4);
-    assert_eq!(Term::from(&term_5), expected_5);
+    assert_eq!(Term::from(term_1), Ok(expected_1));
+    assert_eq!(Term::from(term_2), Ok(expected_2));
+    assert_eq!(Term::from(term_3), Ok(expected_3));
+    assert_eq!(Term::from(term_4).unwrap_err().lines().count(), 2);
+    assert_eq!(Term::from(term_5), Ok(expected_5));
+
+    assert_eq!(Term::from("300 X"), Ok(Some(Term::new(String::from("X"), 300))));
+}
 
+#[test]
+fn test_from_coefficient_too_large() {
+    assert!(Term::from("4294967296 X").unwrap_err().contains("too large"));
 }
 
+#[test]
+fn test_merge() {
+    let merged = Term::merge([Term::new(String::from("A"), 1), Term::new(String::from("B"), 1), Term::new(String::from("A"), 2)]);
+    assert_eq!(merged, HashSet::from([Term::new(String::from("A"), 3), Term::new(String::from("B"), 1)]));
 }
 
+}
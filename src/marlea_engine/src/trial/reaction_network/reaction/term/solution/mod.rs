@@ -54,7 +54,7 @@ impl Display for Solution {
         // Format the ordered vector as a string
         let mut formatted_string = String::new();
         for entry in self.clone().into_iter() {
-            formatted_string.push_str(&format!("{},{},",entry.0.to_string(),entry.1.to_string()));
+            formatted_string.push_str(&format!("{},{},",entry.0,entry.1));
         }
 
         // Write the formatted string to the provided Formatter
@@ -0,0 +1,251 @@
+/// Explicit tau-leaping for `ReactionNetwork`.
+///
+/// Each leap fires a Poisson distributed number of every non critical reaction over a time step `tau`.
+/// `tau` is selected adaptively following Cao, Gillespie and Petzold (2006) so that no propensity is expected to change
+/// by more than a fraction `EPSILON` of its value during the leap.
+/// Reactions which are within `CRITICAL_FIRINGS` firings of exhausting one of their reactants are critical,
+/// and at most one critical reaction fires per leap.
+/// When the selected step is too small to be worth leaping, exact direct method steps are taken instead.
+/// A leap which would drive any species count negative is rejected and retried with half the step.
+/// Leaps never step past the release of delayed products, delayed reactions fired during a leap schedule their products
+/// from the end of the leap.
+
+use rand::Rng;
+use rand_distr::{Distribution, Poisson};
+use super::{ReactionNetwork, sample_exponential, count::Count};
+
+/// Bound on the relative change in propensities over a single leap
+const EPSILON: f64 = 0.03;
+/// Reactions which can fire fewer than this many times before exhausting a reactant are critical
+const CRITICAL_FIRINGS: u64 = 10;
+/// If the selected leap is shorter than this many expected SSA steps fall back to an exact step
+const SSA_FALLBACK_STEPS: f64 = 10.0;
+
+impl<C: Count> ReactionNetwork<C> {
+
+    // Performs a single tau leap, or a single exact step if leaping is not worthwhile
+    pub(super) fn react_tau_leap(&mut self) -> Result<(), String> {
+        self.find_possible_reactions();
+        let total_propensity = self.sum_propensities();
+        if total_propensity <= 0.0 {
+            // nothing can fire but delayed products may still be pending
+            return self.react_direct();
+        }
+
+        // partition possible reactions into critical and non critical reactions
+        let mut critical = Vec::new();
+        let mut non_critical = Vec::new();
+        for index in self.possible_reactions.iter() {
+            if self.max_firings(*index) < CRITICAL_FIRINGS {
+                critical.push(*index);
+            } else {
+                non_critical.push(*index);
+            }
+        }
+        critical.sort_unstable();
+        non_critical.sort_unstable();
+
+        let mut leap = self.select_leap(&non_critical).min(self.get_next_release_time() - self.simulated_time);
+        if (!leap.is_finite() && critical.is_empty()) || leap < SSA_FALLBACK_STEPS / total_propensity {
+            return self.react_direct();
+        }
+
+        let critical_propensity: f64 = critical.iter().map(|index| self.propensities[*index]).sum();
+        loop {
+            let critical_time = sample_exponential(critical_propensity, &mut self.rng);
+            let mut firings: Vec<(usize, u64)> = Vec::new();
+
+            let tau = if leap < critical_time {
+                leap
+            } else {
+                firings.push((self.pick_reaction(&critical, critical_propensity), 1));
+                critical_time
+            };
+
+            for index in &non_critical {
+                let mean = self.propensities[*index] * tau;
+                if mean > 0.0 {
+                    let count = Poisson::new(mean)
+                        .expect("poisson mean is positive and finite")
+                        .sample(&mut self.rng) as u64;
+                    if count > 0 {
+                        firings.push((*index, count));
+                    }
+                }
+            }
+
+            if self.try_apply_firings(&firings)? {
+                self.simulated_time += tau;
+                for (index, count) in &firings {
+                    if self.get_reaction(*index).get_delay().is_some() {
+                        for _ in 0..*count {
+                            self.schedule_products(*index);
+                        }
+                    }
+                }
+                self.release_products_until(self.simulated_time)?;
+                return Ok(());
+            }
+
+            // the leap would have made a count negative so retry with a shorter leap
+            leap = tau / 2.0;
+        }
+    }
+
+    // Returns the number of times a reaction could fire before it exhausts one of its reactants,
+    // bounded by the reactant coefficients since a reactant which is also a product must still be present to fire
+    fn max_firings(&self, index: usize) -> u64 {
+        let mut max_firings = u64::MAX;
+        for (species, coefficient) in self.get_reaction(index).get_reactants() {
+            max_firings = max_firings.min(self.get_count(*species) / coefficient);
+        }
+        return max_firings;
+    }
+
+    // Selects the largest leap for which the expected change and variance in every reactant species
+    // stays within a fraction EPSILON of its count, see Cao, Gillespie and Petzold (2006)
+    fn select_leap(&self, non_critical: &[usize]) -> f64 {
+        // expected change and variance in each species per unit time
+        let species_count = self.get_species().len();
+        let mut drift: Vec<(f64, f64)> = vec![(0.0, 0.0); species_count];
+        for index in non_critical {
+            let propensity = self.propensities[*index];
+            for (species, net_change) in self.get_reaction(*index).get_initiation_changes() {
+                drift[*species].0 += *net_change as f64 * propensity;
+                drift[*species].1 += (net_change * net_change) as f64 * propensity;
+            }
+        }
+
+        // highest order reaction and its coefficient for each reactant species
+        let mut highest_orders: Vec<(u64, u64)> = vec![(0, 0); species_count];
+        for reaction in self.get_reactions() {
+            let order: u64 = reaction.get_reactants().iter().map(|(_, coefficient)| coefficient).sum();
+            for (species, coefficient) in reaction.get_reactants() {
+                let entry = &mut highest_orders[*species];
+                if order > entry.0 || (order == entry.0 && *coefficient > entry.1) {
+                    *entry = (order, *coefficient);
+                }
+            }
+        }
+
+        let mut leap = f64::INFINITY;
+        for index in non_critical {
+            for (species, _) in self.get_reaction(*index).get_reactants() {
+                let (mean, variance) = drift[*species];
+                let (order, coefficient) = highest_orders[*species];
+                let count = self.get_count(*species);
+
+                let bound = (EPSILON * count as f64 / highest_order_factor(order, coefficient, count)).max(1.0);
+                if mean != 0.0 {
+                    leap = leap.min(bound / mean.abs());
+                }
+                if variance != 0.0 {
+                    leap = leap.min(bound * bound / variance);
+                }
+            }
+        }
+
+        return leap;
+    }
+
+    // Picks one of the given reactions weighted by propensity
+    fn pick_reaction(&mut self, reactions: &[usize], total_propensity: f64) -> usize {
+        let mut index = self.rng.gen_range(0.0..total_propensity);
+        for reaction in reactions {
+            let propensity = self.propensities[*reaction];
+            if propensity > index {
+                return *reaction;
+            }
+            index -= propensity;
+        }
+        // floating point rounding can leave a sliver past the final reaction
+        return *reactions.last().expect("picked from an empty set of reactions");
+    }
+
+    // Applies every firing at once,
+    // returns false without changing the solution if any count would become negative,
+    // and an error naming a reaction which increases a count that would overflow
+    fn try_apply_firings(&mut self, firings: &[(usize, u64)]) -> Result<bool, String> {
+        let mut changes: Vec<i128> = vec![0; self.counts.len()];
+        for (index, count) in firings {
+            for (species, net_change) in self.get_reaction(*index).get_initiation_changes() {
+                changes[*species] = (*net_change as i128).checked_mul(*count as i128)
+                    .and_then(|change| changes[*species].checked_add(change))
+                    .ok_or_else(|| self.count_error(*species, *index, "overflowed"))?;
+            }
+        }
+
+        let mut updated_counts = Vec::new();
+        let mut overflowed = None;
+        for (species, change) in changes.into_iter().enumerate() {
+            if change == 0 {
+                continue;
+            }
+            let mut count = self.counts[species].clone();
+            if count.try_apply(change) {
+                updated_counts.push((species, count));
+            } else if change < 0 {
+                return Ok(false);
+            } else if overflowed.is_none() {
+                overflowed = Some(species);
+            }
+        }
+
+        if let Some(species) = overflowed {
+            let reaction = firings.iter()
+                .map(|(index, _)| *index)
+                .find(|index| self.get_reaction(*index).get_initiation_changes().iter().any(|(changed, net_change)| *changed == species && *net_change > 0))
+                .expect("an overflowing count was increased by a reaction");
+            return Err(self.count_error(species, reaction, "overflowed"));
+        }
+
+        for (species, count) in updated_counts {
+            self.counts[species] = count;
+        }
+        return Ok(true);
+    }
+}
+
+/// The factor g from Cao, Gillespie and Petzold relating relative change in a species count
+/// to relative change in the propensity of the highest order reaction it takes part in
+fn highest_order_factor(order: u64, coefficient: u64, count: u64) -> f64 {
+    let x = count as f64;
+    match (order, coefficient) {
+        (2, 2) if count > 1 => 2.0 + 1.0 / (x - 1.0),
+        (3, 2) if count > 1 => 1.5 * (2.0 + 1.0 / (x - 1.0)),
+        (3, 3) if count > 2 => 3.0 + 1.0 / (x - 1.0) + 2.0 / (x - 2.0),
+        _ => order.max(1) as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{SimulationAlgorithm, parse_test_network};
+
+    #[test]
+    fn test_tau_leap_never_negative() {
+        // a large starting population
+        let mut network = parse_test_network("A => B, 1\n2 A => C, 1", "A, 1000000");
+        network.set_algorithm(SimulationAlgorithm::TauLeap);
+        let mut steps = 0;
+        while !network.get_possible_reactions().is_empty() {
+            network.react().unwrap();
+            steps += 1;
+        }
+
+        // every A must have been converted with conservation of mass, species are ordered A, B, C
+        let counts = network.get_counts();
+        assert!(counts[0] <= 1);
+        assert_eq!(counts[0] + counts[1] + 2 * counts[2], 1_000_000);
+
+        // leaping should take far fewer steps than one per reaction
+        assert!(steps < 500_000);
+    }
+
+    #[test]
+    fn test_max_firings_uses_reactant_coefficients() {
+        // 2 A => A + X only changes A by -1 but needs 2 A to fire
+        let network = parse_test_network("2 A => A + X, 1", "A, 3");
+        assert_eq!(network.max_firings(0), 1);
+    }
+}
@@ -1,8 +1,12 @@
-use super::*;
-
-
-#[derive(Eq, PartialEq, Clone)]
-pub enum TrialResult {
-    StableSolution(Solution, i32), 
-    TimelineEntry(Solution, usize),
+/// Messages sent from a running trial back to the engine 
+/// - `StableSolution` carries the final count of every species in the order of the trial's compiled network,
+///                    the number of steps taken, the simulated time at which the trial stabilized,
+///                    the index of every threshold which was reached when the trial stopped, and the id of the trial
+/// - `Cancelled` carries the id of a trial which stopped before becoming stable because its run was cancelled
+/// - `Failed` carries the error which stopped a trial, such as a reaction overflowing a count, and the id of the trial
+#[derive(PartialEq, Clone)]
+pub enum TrialResult<C = u64> {
+    StableSolution(Vec<C>, i32, f64, Vec<usize>, usize),
+    Cancelled(usize),
+    Failed(String, usize),
 }
\ No newline at end of file
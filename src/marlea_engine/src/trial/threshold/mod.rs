@@ -0,0 +1,145 @@
+use std::fmt::Display;
+use crate::trial::reaction_network::{count::Count, reaction::term::solution::{Solution, Species}};
+
+/// How a species count is compared against a threshold value
+/// - `LT`: less than
+/// - `LE`: less than or equal to
+/// - `GE`: greater than or equal to
+/// - `GT`: greater than
+/// - `EQ`: equal to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    LT,
+    LE,
+    GE,
+    GT,
+    EQ,
+}
+
+impl std::str::FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_ref() {
+            "LT" => Ok(Comparison::LT),
+            "LE" => Ok(Comparison::LE),
+            "GE" => Ok(Comparison::GE),
+            "GT" => Ok(Comparison::GT),
+            "EQ" => Ok(Comparison::EQ),
+            _ => Err(format!("Invalid threshold comparison '{}', expected one of LT, LE, GE, GT, EQ, or N", s)),
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Comparison::LT => "LT",
+            Comparison::LE => "LE",
+            Comparison::GE => "GE",
+            Comparison::GT => "GT",
+            Comparison::EQ => "EQ",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A condition on the count of a single species which stops a trial as soon as it holds, as in Aleae.
+/// Thresholds are written after the initial count of a species in the init file, e.g. `Y, 0, GE 10`,
+/// where `N` means the species has no threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Threshold {
+    species: Species,
+    comparison: Comparison,
+    value: u64,
+}
+
+impl Threshold {
+
+    pub fn new(species_name: String, comparison: Comparison, value: u64) -> Self {
+        return Self{species: Species::Name(species_name), comparison, value};
+    }
+
+    /// parses a threshold for some species from the fields following its initial count,
+    /// either `<comparison> <value>` in a single field or in two fields, Aleae's `<value> <comparison>` order is also accepted.
+    /// Returns None for `N`, panics if the threshold is malformed.
+    pub fn from(species_name: &str, fields: &[&str]) -> Option<Self> {
+        return Self::parse(species_name, fields).unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    /// parses the threshold written after the initial count of a species, None if it is `N`
+    /// returns an error if the threshold is malformed
+    pub fn parse(species_name: &str, fields: &[&str]) -> Result<Option<Self>, String> {
+        let tokens: Vec<&str> = fields.iter().flat_map(|field| field.split_whitespace()).collect();
+        let invalid = || {
+            format!("Invalid threshold '{}' for species {} - expected a comparison and a count such as 'GE 10', or 'N' for no threshold", fields.join(" "), species_name)
+        };
+
+        let (comparison, value) = match tokens.as_slice() {
+            [none] if none.eq_ignore_ascii_case("N") => return Ok(None),
+            [comparison, value] | [value, comparison] if comparison.parse::<Comparison>().is_ok() => (comparison, value),
+            _ => return Err(invalid()),
+        };
+
+        let comparison = comparison.parse::<Comparison>().map_err(|_| invalid())?;
+        let value = value.parse::<u64>().map_err(|_| invalid())?;
+        return Ok(Some(Self::new(species_name.to_string(), comparison, value)));
+    }
+
+    pub fn get_species_name(&self) -> &Species {
+        return &self.species;
+    }
+
+    /// returns true if the count of this threshold's species in some solution satisfies the threshold
+    pub fn is_reached(&self, solution: &Solution) -> bool {
+        let count = match solution.species_counts.get(&self.species) {
+            Some(Species::Count(count)) => *count,
+            _ => 0,
+        };
+
+        return self.is_satisfied_by(&count);
+    }
+
+    /// returns true if a count of this threshold's species satisfies the threshold
+    pub fn is_satisfied_by<C: Count>(&self, count: &C) -> bool {
+        let ordering = count.cmp(&C::from_u64(self.value));
+        return match self.comparison {
+            Comparison::LT => ordering.is_lt(),
+            Comparison::LE => ordering.is_le(),
+            Comparison::GE => ordering.is_ge(),
+            Comparison::GT => ordering.is_gt(),
+            Comparison::EQ => ordering.is_eq(),
+        };
+    }
+}
+
+impl Display for Threshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.species, self.comparison, self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(Threshold::from("Y", &["GE 10"]), Some(Threshold::new(String::from("Y"), Comparison::GE, 10)));
+        assert_eq!(Threshold::from("Y", &["lt", "3"]), Some(Threshold::new(String::from("Y"), Comparison::LT, 3)));
+        assert_eq!(Threshold::from("Y", &["5", "EQ"]), Some(Threshold::new(String::from("Y"), Comparison::EQ, 5)));
+        assert_eq!(Threshold::from("Y", &["N"]), None);
+    }
+
+    #[test]
+    fn test_threshold_reached() {
+        let solution = Solution{species_counts: HashMap::from([(Species::Name(String::from("Y")), Species::Count(10))])};
+
+        assert!(Threshold::new(String::from("Y"), Comparison::GE, 10).is_reached(&solution));
+        assert!(!Threshold::new(String::from("Y"), Comparison::GT, 10).is_reached(&solution));
+        assert!(Threshold::new(String::from("Y"), Comparison::EQ, 10).is_reached(&solution));
+        assert!(!Threshold::new(String::from("Y"), Comparison::LT, 10).is_reached(&solution));
+        assert!(Threshold::new(String::from("Y"), Comparison::LE, 10).is_reached(&solution));
+    }
+}
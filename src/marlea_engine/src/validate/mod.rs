@@ -0,0 +1,277 @@
+/// # Description
+/// A linter for input and init files, run by the `validate` query.
+/// Besides every syntax error found by the parser it reports problems which parse but are likely to be mistakes:
+/// - reactions which are written more than once, an error if the duplicate policy rejects them
+/// - reactions with a rate of 0, which never fire
+/// - reactions which never use up any of their reactants, such as `A => A + B`, which fire forever once possible so trials never stabilize
+/// - species which start at 0 and are never produced, so no reaction with them as reactants can fire
+/// - species which are never produced but are consumed, so reactions using them stop once their initial count is used up
+/// - species which are never consumed and only accumulate
+/// - init file entries for species which are in no reaction, and malformed initial counts or thresholds
+///
+/// Every problem is a `Diagnostic` pointing at the line and column it was found at, errors make the files invalid.
+
+use std::collections::{BTreeMap, BTreeSet};
+use crate::parser::{self, diagnostic::{Diagnostic, Severity}, tokenizer::{Token, TokenKind, tokenize}};
+use crate::supported_file_type::SupportedFileType;
+use crate::trial::reaction_network::duplicates::{DuplicatePolicy, KEEP_HINT, REJECT_HINT, find_repeated, describe_repeated};
+use crate::trial::reaction_network::reaction::{Reaction, label::Direction};
+
+const HANG_HINT: &str = "a reaction which should fire at all times may be written with null reactants, e.g. ' => B, 1', which does not hang";
+
+/// Validates an input file and an optional init file, returning every diagnostic found in both,
+/// those of the input file first, each sorted by line and column.
+/// A file which cannot be read, or is not a CSV file, is reported with a single diagnostic instead of being linted
+pub fn validate(input_path: String, init_path: Option<String>, duplicates: DuplicatePolicy) -> Vec<Diagnostic> {
+    let input_text = SupportedFileType::from(input_path.clone()).read_text();
+    let init_text = init_path.clone().map(|path| SupportedFileType::from(path).read_text()).transpose();
+
+    return match (input_text, init_text) {
+        (Ok(input_text), Ok(init_text)) => lint(&input_path, &input_text, init_path.as_deref().zip(init_text.as_deref()), duplicates),
+        (input_text, init_text) => input_text.err().into_iter().chain(init_text.err()).flatten().collect(),
+    };
+}
+
+/// Lints the text of an input file and the path and text of an optional init file, as `validate()` does
+pub fn lint(input_file: &str, input_text: &str, init: Option<(&str, &str)>, duplicates: DuplicatePolicy) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = input_text.lines().collect();
+    let (reactions, mut diagnostics) = parser::parse_reactions(input_file, input_text);
+    let linter = Linter{file: input_file, lines: &lines, reactions: &reactions};
+
+    // every species in a reaction starts at 0 unless the init file sets it
+    let mut initial_counts: BTreeMap<String, u64> = linter.species().into_iter().map(|name| (name, 0)).collect();
+    let mut init_diagnostics = Vec::new();
+    if let Some((init_file, init_text)) = init {
+        init_diagnostics = lint_init(init_file, init_text, &mut initial_counts);
+    }
+
+    diagnostics.extend(linter.repeated_reactions(duplicates));
+    diagnostics.extend(linter.zero_rates());
+    diagnostics.extend(linter.unbalanced_species(&initial_counts));
+    diagnostics.extend(linter.endless_reactions(&initial_counts));
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()));
+
+    init_diagnostics.sort_by_key(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()));
+    diagnostics.extend(init_diagnostics);
+    return diagnostics;
+}
+
+// describes a reaction for a diagnostic by its label, or written with species names if it has none,
+// e.g. `'r1'`, `'A <=> B, reverse'`, or `[A => B]`
+fn describe(reaction: &Reaction) -> String {
+    return match reaction.get_label() {
+        Some(label) => format!("'{}'", label),
+        None => format!("[{}]", reaction),
+    };
+}
+
+// lints the reactions which parsed without errors, pointing back at the lines they were written on
+struct Linter<'a> {
+    file: &'a str,
+    lines: &'a [&'a str],
+    reactions: &'a [(usize, Reaction)],
+}
+
+impl<'a> Linter<'a> {
+
+    fn diagnostic(&self, severity: Severity, line: usize, (column, length): (usize, usize), message: String, hint: Option<&str>) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(severity, self.file, line, column, length, self.lines[line - 1], message);
+        diagnostic.set_hint(hint.map(str::to_string));
+        return diagnostic;
+    }
+
+    // returns the span of a whole line, ignoring surrounding whitespace
+    fn line_span(&self, line: usize) -> (usize, usize) {
+        let tokens = tokenize(self.lines[line - 1]);
+        return match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => (first.get_column(), last.get_end() - first.get_column()),
+            _ => (1, 1),
+        };
+    }
+
+    // returns the span of the first mention of a species in a line
+    fn species_span(&self, line: usize, name: &str) -> (usize, usize) {
+        return tokenize(self.lines[line - 1]).iter()
+            .find(|token| *token.get_kind() == TokenKind::Name(name.to_string()))
+            .map_or_else(|| self.line_span(line), |token| (token.get_column(), token.get_length()));
+    }
+
+    // returns the span of a comma separated field of a line, field 1 is the rate, or the forward rate of a reversible reaction
+    fn field_span(&self, line: usize, field: usize) -> (usize, usize) {
+        let tokens = tokenize(self.lines[line - 1]);
+        let field_tokens: Vec<&Token> = tokens.split(|token| *token.get_kind() == TokenKind::Comma).nth(field).unwrap_or_default().iter().collect();
+        return match (field_tokens.first(), field_tokens.last()) {
+            (Some(first), Some(last)) => (first.get_column(), last.get_end() - first.get_column()),
+            _ => self.line_span(line),
+        };
+    }
+
+    // returns the name of every species in any reaction
+    fn species(&self) -> BTreeSet<String> {
+        return self.reactions.iter()
+            .flat_map(|(_, reaction)| reaction.get_reactants().iter().chain(reaction.get_products()))
+            .map(|term| term.get_species_name().to_string())
+            .collect();
+    }
+
+    // reports every group of duplicate or parallel reactions at the first repeat, errors if they are rejected
+    fn repeated_reactions(&self, duplicates: DuplicatePolicy) -> Vec<Diagnostic> {
+        let (severity, hint) = match duplicates {
+            DuplicatePolicy::Reject => (Severity::Error, REJECT_HINT),
+            DuplicatePolicy::Keep => (Severity::Warning, KEEP_HINT),
+            DuplicatePolicy::Merge => (Severity::Warning, "they are merged into a single reaction by summing their rates"),
+        };
+
+        return find_repeated(self.reactions).iter()
+            .map(|group| {
+                let line = self.reactions[group[1]].0;
+                self.diagnostic(severity, line, self.line_span(line), describe_repeated(self.reactions, group), Some(hint))
+            })
+            .collect();
+    }
+
+    // reports every reaction with a rate of 0
+    fn zero_rates(&self) -> Vec<Diagnostic> {
+        return self.reactions.iter()
+            .filter(|(_, reaction)| reaction.get_reaction_rate() == 0.0)
+            .map(|(line, reaction)| {
+                let field = match reaction.get_label().and_then(|label| label.get_direction()) {
+                    Some(Direction::Reverse) => 2,
+                    _ => 1,
+                };
+                self.diagnostic(Severity::Warning, *line, self.field_span(*line, field), format!("reaction {} has a rate of 0 and never fires", describe(reaction)), None)
+            })
+            .collect();
+    }
+
+    // returns the species which some reaction that can fire produces, and those it consumes
+    fn changed_species(&self) -> (BTreeSet<String>, BTreeSet<String>) {
+        let mut produced = BTreeSet::new();
+        let mut consumed = BTreeSet::new();
+        for (_, reaction) in self.reactions.iter().filter(|(_, reaction)| reaction.get_reaction_rate() > 0.0) {
+            for (species, net_change) in reaction.get_net_changes() {
+                if net_change > 0 {
+                    produced.insert(species.to_string());
+                } else {
+                    consumed.insert(species.to_string());
+                }
+            }
+        }
+        return (produced, consumed);
+    }
+
+    // returns the species which start at 0 and are never produced, so no reaction consuming them can fire
+    fn absent_species(&self, initial_counts: &BTreeMap<String, u64>) -> BTreeSet<String> {
+        let (produced, _) = self.changed_species();
+        return initial_counts.iter()
+            .filter(|(name, count)| **count == 0 && !produced.contains(*name))
+            .map(|(name, _)| name.clone())
+            .collect();
+    }
+
+    // reports species which start at 0 and are never produced, and species which start above 0 and are consumed but never produced,
+    // at their first use as a reactant, and species which are produced but never consumed, at their first use as a product
+    fn unbalanced_species(&self, initial_counts: &BTreeMap<String, u64>) -> Vec<Diagnostic> {
+        let (produced, consumed) = self.changed_species();
+        let absent = self.absent_species(initial_counts);
+        let mut diagnostics = Vec::new();
+
+        let first_line = |name: &str, reactants: bool| self.reactions.iter()
+            .find(|(_, reaction)| {
+                let side = if reactants { reaction.get_reactants() } else { reaction.get_products() };
+                side.iter().any(|term| term.get_species_name().to_string() == name)
+            })
+            .map(|(line, _)| *line);
+
+        for name in self.species() {
+            if absent.contains(&name) {
+                if let Some(line) = first_line(&name, true) {
+                    diagnostics.push(self.diagnostic(Severity::Warning, line, self.species_span(line, &name),
+                        format!("species {} starts at 0 and is never produced, so no reaction with it as a reactant can fire", name),
+                        Some("set an initial count for it in the init file")));
+                }
+            } else if consumed.contains(&name) && !produced.contains(&name) {
+                if let Some(line) = first_line(&name, true) {
+                    diagnostics.push(self.diagnostic(Severity::Warning, line, self.species_span(line, &name),
+                        format!("species {} is consumed but never produced, reactions using it stop once its initial count of {} is used up", name, initial_counts[&name]),
+                        None));
+                }
+            } else if produced.contains(&name) && !consumed.contains(&name) {
+                if let Some(line) = first_line(&name, false) {
+                    diagnostics.push(self.diagnostic(Severity::Note, line, self.species_span(line, &name),
+                        format!("species {} is never consumed, it only accumulates", name), None));
+                }
+            }
+        }
+        return diagnostics;
+    }
+
+    // reports reactions which can fire but never use up any of their reactants, since no reaction consumes them,
+    // once possible these fire forever and trials never stabilize
+    fn endless_reactions(&self, initial_counts: &BTreeMap<String, u64>) -> Vec<Diagnostic> {
+        let (_, consumed) = self.changed_species();
+        let absent = self.absent_species(initial_counts);
+
+        return self.reactions.iter()
+            .filter(|(_, reaction)| {
+                let reactants: Vec<String> = reaction.get_reactants().iter().map(|term| term.get_species_name().to_string()).collect();
+                reaction.get_reaction_rate() > 0.0
+                    && !reactants.is_empty()
+                    && reactants.iter().all(|name| !consumed.contains(name) && !absent.contains(name))
+            })
+            .map(|(line, reaction)| self.diagnostic(Severity::Warning, *line, self.line_span(*line),
+                format!("reaction {} never uses up its reactants and nothing else consumes them, so once possible it fires forever and the simulation will hang", describe(reaction)),
+                Some(HANG_HINT)))
+            .collect();
+    }
+}
+
+// lints every line of an init file, setting the initial count of every species in a reaction which it sets
+fn lint_init(file: &str, text: &str, initial_counts: &mut BTreeMap<String, u64>) -> Vec<Diagnostic> {
+    let (_, diagnostics) = parser::parse_init(file, text, initial_counts);
+    return diagnostics;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // returns the severity, line, and column of every diagnostic
+    fn found(diagnostics: &[Diagnostic]) -> Vec<(Severity, usize, usize)> {
+        return diagnostics.iter()
+            .map(|diagnostic| (diagnostic.get_severity(), diagnostic.get_line(), diagnostic.get_column()))
+            .collect();
+    }
+
+    #[test]
+    fn test_lint_network() {
+        let input = "A + B => C, 1\nC => D, 0\nA + B => C, 2\nX => X + Y, 1\n";
+        let init = "A, 10\nB, 5, GE 2\nZ, 3\nX, lots\n";
+        let diagnostics = lint("input.csv", input, Some(("init.csv", init)), DuplicatePolicy::Reject);
+
+        assert_eq!(found(&diagnostics), vec![
+            (Severity::Warning, 1, 1),  // A is only consumed from its initial count
+            (Severity::Warning, 1, 5),  // B is only consumed from its initial count
+            (Severity::Note, 1, 10),    // C is never consumed, the only reaction consuming it has a rate of 0
+            (Severity::Warning, 2, 9),  // C => D has a rate of 0
+            (Severity::Error, 3, 1),    // A + B => C is written twice
+            (Severity::Warning, 4, 1),  // X starts at 0 and is never produced
+            (Severity::Note, 4, 10),    // Y is never consumed
+            (Severity::Warning, 3, 1),  // Z is in no reaction
+            (Severity::Error, 4, 4),    // the count of X is invalid
+        ]);
+        assert_eq!(diagnostics[7].get_file(), "init.csv");
+
+        // once X is present X => X + Y fires forever
+        let diagnostics = lint("input.csv", "X => X + Y, 1\n", Some(("init.csv", "X, 1")), DuplicatePolicy::Keep);
+        assert_eq!(found(&diagnostics), vec![(Severity::Warning, 1, 1), (Severity::Note, 1, 10)]);
+        assert_eq!(diagnostics[0].get_hint(), Some(HANG_HINT));
+    }
+
+    #[test]
+    fn test_unsupported_input() {
+        let diagnostics = validate(String::from("input.json"), None, DuplicatePolicy::Keep);
+        assert_eq!(found(&diagnostics), vec![(Severity::Error, 0, 0)]);
+        assert_eq!(diagnostics[0].get_message(), "unsupported input format for input.json, expected a CSV file");
+    }
+}